@@ -0,0 +1,386 @@
+use super::Result;
+use eyre::eyre;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+/// Name of the cgroup scinit moves itself into when it manages its own cgroup
+const SUPERVISOR_CGROUP: &str = "scinit";
+
+/// Default `cpu.max` period (µs)
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// cgroup v2 settings for one service
+///
+/// Each service gets a cgroup below the delegated root; limits are written
+/// there and therefore cover all of the service's processes. Every spawn
+/// goes into a fresh leaf below it, so an overlapping restart can kill one
+/// generation without touching the other.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CgroupConfig {
+    /// The service's cgroup directory
+    pub path: PathBuf,
+    /// `memory.max` in bytes
+    pub memory_max: Option<u64>,
+    /// `cpu.max`
+    pub cpu_max: Option<CpuMax>,
+    /// `pids.max`
+    pub pids_max: Option<u64>,
+}
+
+impl CgroupConfig {
+    /// Controllers the limits need enabled in the parent cgroup
+    pub fn controllers(&self) -> Vec<&'static str> {
+        let mut controllers = Vec::new();
+        if self.memory_max.is_some() {
+            controllers.push("memory");
+        }
+        if self.cpu_max.is_some() {
+            controllers.push("cpu");
+        }
+        if self.pids_max.is_some() {
+            controllers.push("pids");
+        }
+        controllers
+    }
+}
+
+/// CPU bandwidth limit, given on the command line as a number of CPUs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuMax {
+    /// Runtime allowed per period (µs)
+    pub quota: u64,
+    /// Period (µs)
+    pub period: u64,
+}
+
+impl fmt::Display for CpuMax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.quota, self.period)
+    }
+}
+
+impl FromStr for CpuMax {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let cpus: f64 = s
+            .trim()
+            .parse()
+            .map_err(|e| eyre!("Invalid CPU limit '{}': {}", s, e))?;
+        if !cpus.is_finite() || cpus < 0.01 {
+            return Err(eyre!("Invalid CPU limit '{}': expected at least 0.01 CPUs", s));
+        }
+        Ok(CpuMax {
+            quota: (cpus * CPU_PERIOD_US as f64).round() as u64,
+            period: CPU_PERIOD_US,
+        })
+    }
+}
+
+/// Parses a byte size with an optional `K`, `M`, `G` or `T` suffix (powers of 1024)
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let shift = match c.to_ascii_uppercase() {
+                'K' => 10,
+                'M' => 20,
+                'G' => 30,
+                'T' => 40,
+                _ => return Err(eyre!("Invalid size '{}': unknown suffix '{}'", s, c)),
+            };
+            (&s[..i], shift)
+        }
+        _ => (s, 0),
+    };
+    let value: u64 = digits.parse().map_err(|e| eyre!("Invalid size '{}': {}", s, e))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| eyre!("Invalid size '{}': too large", s))
+}
+
+/// Prepares the delegated cgroup services are created in
+///
+/// Without an explicit root, scinit uses the cgroup it runs in. Since cgroup
+/// v2 only allows processes in leaves once controllers are enabled, scinit
+/// then moves itself into a `scinit` child cgroup first.
+///
+/// # Arguments
+/// * `root` - Delegated cgroup directory, or None for scinit's own cgroup
+/// * `controllers` - Controllers to enable for the service cgroups
+///
+/// # Returns
+/// * `Result<PathBuf>` - The cgroup directory to create service cgroups in
+pub fn prepare_root(root: Option<&Path>, controllers: &[&str]) -> Result<PathBuf> {
+    let own = own_cgroup()?;
+    let root = match root {
+        Some(root) => root.to_path_buf(),
+        None => own.clone(),
+    };
+    if !root.join("cgroup.procs").exists() {
+        return Err(eyre!("{} is not a cgroup v2 directory", root.display()));
+    }
+
+    if own == root {
+        let supervisor = root.join(SUPERVISOR_CGROUP);
+        create_dir(&supervisor)?;
+        write_file(&supervisor.join("cgroup.procs"), "0")?;
+        info!("Moved scinit into cgroup {}", supervisor.display());
+    }
+
+    if !controllers.is_empty() {
+        let available = std::fs::read_to_string(root.join("cgroup.controllers"))
+            .map_err(|e| eyre!("Failed to read controllers of {}: {}", root.display(), e))?;
+        let available: Vec<&str> = available.split_whitespace().collect();
+        if let Some(missing) = controllers.iter().find(|c| !available.contains(c)) {
+            return Err(eyre!("The {} controller is not delegated to cgroup {}", missing, root.display()));
+        }
+        let enable: Vec<String> = controllers.iter().map(|c| format!("+{}", c)).collect();
+        write_file(&root.join("cgroup.subtree_control"), &enable.join(" "))?;
+    }
+
+    Ok(root)
+}
+
+/// Finds the cgroup v2 directory scinit runs in
+fn own_cgroup() -> Result<PathBuf> {
+    let mount = find_mount(|fs_type, _| fs_type == "cgroup2").ok_or_else(|| eyre!("No cgroup v2 filesystem is mounted"))?;
+    let own = own_cgroup_path(|controllers| controllers.is_empty())
+        .ok_or_else(|| eyre!("scinit is not in a cgroup v2 hierarchy"))?;
+    Ok(mount.join(own.trim_start_matches('/')))
+}
+
+/// Finds a mount point in `/proc/self/mountinfo` by filesystem type and super options
+fn find_mount(matches: impl Fn(&str, &str) -> bool) -> Option<PathBuf> {
+    let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    mountinfo.lines().find_map(|line| {
+        // Fields after " - " are the filesystem type, source and super options
+        let (fields, fs) = line.split_once(" - ")?;
+        let mut fs = fs.split_whitespace();
+        let (fs_type, options) = (fs.next()?, fs.nth(1)?);
+        matches(fs_type, options).then(|| fields.split_whitespace().nth(4).map(PathBuf::from))?
+    })
+}
+
+/// Finds scinit's path in the hierarchy whose controller list matches
+fn own_cgroup_path(matches: impl Fn(&str) -> bool) -> Option<String> {
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    cgroups.lines().find_map(|line| {
+        let mut fields = line.splitn(3, ':');
+        let (_, controllers, path) = (fields.next()?, fields.next()?, fields.next()?);
+        matches(controllers).then(|| path.to_string())
+    })
+}
+
+/// A service's cgroup, holding one leaf per spawned generation
+#[derive(Debug)]
+pub struct ServiceCgroup {
+    /// Limits and location
+    config: CgroupConfig,
+    /// Number of the next generation
+    next_generation: u64,
+}
+
+impl ServiceCgroup {
+    /// Creates the service cgroup and applies its limits
+    ///
+    /// # Arguments
+    /// * `config` - Location and limits
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The service cgroup or an error naming the failed setting
+    pub fn create(config: CgroupConfig) -> Result<Self> {
+        create_dir(&config.path)?;
+        if let Some(memory_max) = config.memory_max {
+            write_file(&config.path.join("memory.max"), &memory_max.to_string())?;
+        }
+        if let Some(cpu_max) = config.cpu_max {
+            write_file(&config.path.join("cpu.max"), &cpu_max.to_string())?;
+        }
+        if let Some(pids_max) = config.pids_max {
+            write_file(&config.path.join("pids.max"), &pids_max.to_string())?;
+        }
+        debug!("Created cgroup {}", config.path.display());
+        Ok(Self { config, next_generation: 0 })
+    }
+
+    /// Service cgroup directory
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    /// Creates the leaf cgroup for the next process
+    ///
+    /// Leaves of earlier generations that have emptied out are removed.
+    pub fn new_generation(&mut self) -> Result<Cgroup> {
+        self.remove_empty_leaves();
+        let path = self.config.path.join(format!("gen-{}", self.next_generation));
+        self.next_generation += 1;
+        create_dir(&path)?;
+        Ok(Cgroup { path })
+    }
+
+    /// Removes the service cgroup once its leaves have emptied out
+    pub fn remove(&self) {
+        self.remove_empty_leaves();
+        Cgroup { path: self.config.path.clone() }.remove();
+    }
+
+    fn remove_empty_leaves(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.config.path) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let leaf = Cgroup { path: entry.path() };
+            if entry.file_type().is_ok_and(|t| t.is_dir()) && !leaf.is_populated() {
+                leaf.remove();
+            }
+        }
+    }
+}
+
+/// Leaf cgroup holding one generation of a service's processes
+#[derive(Debug)]
+pub struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Opens `cgroup.procs` so the child can move itself in between fork and exec
+    ///
+    /// The child writes `0` to the returned file, which moves the writer.
+    pub fn open_procs(&self) -> Result<File> {
+        let path = self.path.join("cgroup.procs");
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| eyre!("Failed to open {}: {}", path.display(), e))
+    }
+
+    /// Checks whether any process is left in the cgroup
+    pub fn is_populated(&self) -> bool {
+        std::fs::read_to_string(self.path.join("cgroup.events"))
+            .map(|events| events.lines().any(|line| line == "populated 1"))
+            .unwrap_or(false)
+    }
+
+    /// Processes currently in the cgroup
+    pub fn pids(&self) -> Vec<Pid> {
+        std::fs::read_to_string(self.path.join("cgroup.procs"))
+            .map(|procs| procs.lines().filter_map(|pid| pid.parse().ok()).map(Pid::from_raw).collect())
+            .unwrap_or_default()
+    }
+
+    /// SIGKILLs every process in the cgroup, including ones that left the process group
+    ///
+    /// Uses `cgroup.kill` where the kernel has it (5.14+), otherwise signals
+    /// the members of `cgroup.procs` until none are left to signal.
+    pub fn kill(&self) -> Result<()> {
+        match write_file(&self.path.join("cgroup.kill"), "1") {
+            Ok(()) => return Ok(()),
+            Err(e) => debug!("cgroup.kill unavailable, signalling members instead: {}", e),
+        }
+
+        // Forks racing with the kill show up in later reads
+        for _ in 0..10 {
+            let pids = self.pids();
+            if pids.is_empty() {
+                break;
+            }
+            for pid in pids {
+                if let Err(e) = kill(pid, Signal::SIGKILL) {
+                    if e != nix::Error::ESRCH {
+                        warn!("Failed to send SIGKILL to {} in cgroup {}: {}", pid, self.path.display(), e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the cgroup if it is empty
+    pub fn remove(&self) {
+        if let Err(e) = std::fs::remove_dir(&self.path) {
+            debug!("Could not remove cgroup {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Leaf cgroup directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Reads the `oom_kill` counter of a memory cgroup
+///
+/// On cgroup v2 the counter comes from `memory.events` and covers the whole
+/// subtree; it is missing unless the memory controller is enabled for the
+/// cgroup. On v1 it comes from `memory.oom_control`.
+pub fn oom_kill_count(cgroup: &Path) -> Option<u64> {
+    ["memory.events", "memory.oom_control"].iter().find_map(|file| {
+        let events = std::fs::read_to_string(cgroup.join(file)).ok()?;
+        events.lines().find_map(|line| line.strip_prefix("oom_kill ")?.trim().parse().ok())
+    })
+}
+
+fn create_dir(path: &Path) -> Result<()> {
+    match std::fs::create_dir(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+        Err(e) => Err(eyre!("Failed to create cgroup {}: {}", path.display(), e)),
+    }
+}
+
+fn write_file(path: &Path, value: &str) -> Result<()> {
+    std::fs::write(path, value).map_err(|e| eyre!("Failed to write '{}' to {}: {}", value, path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_parsing() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("64k").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("1G").unwrap(), 1 << 30);
+        assert!(parse_size("1X").is_err());
+        assert!(parse_size("M").is_err());
+
+        assert_eq!("1.5".parse::<CpuMax>().unwrap().to_string(), "150000 100000");
+        assert!("0".parse::<CpuMax>().is_err());
+    }
+
+    #[test]
+    fn test_service_cgroup_layout() {
+        // A plain directory stands in for cgroupfs
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = CgroupConfig {
+            path: temp_dir.path().join("main"),
+            memory_max: Some(1 << 20),
+            cpu_max: None,
+            pids_max: Some(64),
+        };
+        assert_eq!(config.controllers(), vec!["memory", "pids"]);
+
+        let mut service = ServiceCgroup::create(config).unwrap();
+        let path = temp_dir.path().join("main");
+        assert_eq!(std::fs::read_to_string(path.join("memory.max")).unwrap(), "1048576");
+        assert_eq!(std::fs::read_to_string(path.join("pids.max")).unwrap(), "64");
+
+        let first = service.new_generation().unwrap();
+        let second = service.new_generation().unwrap();
+        assert_ne!(first.path(), second.path());
+        assert!(!first.path().exists(), "empty leaves are cleaned up");
+        assert!(second.path().is_dir());
+
+        assert_eq!(oom_kill_count(&path), None);
+        std::fs::write(path.join("memory.events"), "low 0\nhigh 0\nmax 3\noom 1\noom_kill 2\n").unwrap();
+        assert_eq!(oom_kill_count(&path), Some(2));
+    }
+}
@@ -1,11 +1,24 @@
-use clap::Parser;
+use clap::parser::ArgMatches;
+use clap::{Parser, Subcommand};
 use eyre::eyre;
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::time::Duration;
 
-use crate::file_watcher::FileWatchConfig;
-use crate::port_manager::PortBindingConfig;
+use crate::cgroups::{parse_size, CgroupConfig, CpuMax};
+use crate::config_file::FileConfig;
+use crate::env::{parse_env_assignment, EnvConfig};
+use crate::file_watcher::{build_globset, resolve_executable, FileWatchConfig, WatchBackend};
+use crate::health::{LivenessConfig, Probe, ReadinessConfig};
+use crate::notify::{NotifyAddress, NotifyConfig};
+use crate::port_manager::{ListenerSpec, PortBindingConfig};
+use crate::process_manager::RestartMode;
+use crate::restart_policy::{RestartPolicy, RestartPolicyConfig, EXIT_CODE_RESTARTS_EXHAUSTED};
+use crate::rlimits::RlimitSpec;
+use crate::signals::{parse_signal, parse_signal_rewrite, parse_signal_target, Signal, SignalPolicy, SignalTarget};
+use crate::supervisor::{ServiceExitPolicy, ServiceSpec, PRIMARY_SERVICE};
+use crate::users::Credentials;
 
 type Result<T> = color_eyre::eyre::Result<T>;
 
@@ -14,14 +27,48 @@ type Result<T> = color_eyre::eyre::Result<T>;
 #[command(name = "scinit")]
 #[command(about = "A live-reloading init system for managing subprocesses")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    /// Configuration file; flags given on the command line override its values
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
     /// Enable live-reload functionality
     #[arg(long)]
     pub live_reload: bool,
 
-    /// Path to watch for changes (default: executable path)
+    /// File or directory to watch for changes (repeatable; default: executable path)
+    #[arg(long = "watch", visible_alias = "watch-path", value_name = "PATH")]
+    pub watch_paths: Vec<PathBuf>,
+
+    /// Watch directories recursively
     #[arg(long)]
-    pub watch_path: Option<PathBuf>,
+    pub recursive: bool,
+
+    /// Only restart for changed files in watched directories matching this glob, e.g. '**/*.rs' (repeatable)
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Don't restart for changed files in watched directories matching this glob, e.g. 'target/**' (repeatable)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Don't skip files ignored by .gitignore and .ignore files
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// How to detect file changes; auto polls where native notifications are found not to work, checked by briefly
+    /// creating a .scinit-probe-<pid> file in one writable watched directory per file system
+    #[arg(long, value_enum, default_value = "auto")]
+    pub watch_backend: WatchBackend,
+
+    /// Time between scans of polled paths (ms)
+    #[arg(long, default_value = "1000")]
+    pub watch_poll_interval_ms: u64,
+
+    /// Shell command to run on file changes, e.g. 'cargo build'; the command only restarts if it succeeds
+    #[arg(long, value_name = "COMMAND")]
+    pub on_change_run: Option<String>,
 
     /// Comma-separated list of ports to bind
     #[arg(long, value_delimiter = ',')]
@@ -31,33 +78,219 @@ pub struct Cli {
     #[arg(long, default_value = "127.0.0.1")]
     pub bind_addr: String,
 
+    /// Additional listener to bind: tcp://ADDR:PORT, udp://ADDR:PORT or unix:///PATH (repeatable)
+    #[arg(long = "listen", value_name = "SPEC")]
+    pub listeners: Vec<ListenerSpec>,
+
+    /// Pass bound ports systemd-style: fd 3 and up with LISTEN_FDS/LISTEN_PID/LISTEN_FDNAMES
+    #[arg(long)]
+    pub socket_activation: bool,
+
     /// Debounce time for file changes (ms)
     #[arg(long, default_value = "500")]
     pub debounce_ms: u64,
 
-    /// Delay before restart after graceful shutdown (ms)
+    /// Delay before restart after graceful shutdown, or settle time of the new process in overlap mode (ms)
     #[arg(long, default_value = "1000")]
     pub restart_delay_ms: u64,
 
+    /// How to replace the running process on restart
+    #[arg(long, value_enum, default_value = "stop-start")]
+    pub restart_mode: RestartMode,
+
+    /// Readiness probe: tcp://HOST:PORT, http://HOST:PORT/PATH, exec:COMMAND or file:PATH
+    #[arg(long, value_name = "PROBE")]
+    pub ready_probe: Option<Probe>,
+
+    /// Time between readiness probe attempts (ms)
+    #[arg(long, default_value = "500")]
+    pub ready_interval_ms: u64,
+
+    /// Timeout for a single readiness probe attempt (ms)
+    #[arg(long, default_value = "1000")]
+    pub ready_timeout_ms: u64,
+
+    /// How long a new process may take to become ready during an overlapping restart (seconds)
+    #[arg(long, default_value = "60")]
+    pub ready_startup_timeout_secs: u64,
+
+    /// Liveness probe; the process is restarted when it keeps failing (same formats as --ready-probe)
+    #[arg(long, value_name = "PROBE")]
+    pub live_probe: Option<Probe>,
+
+    /// Time between liveness probe attempts (ms)
+    #[arg(long, default_value = "10000")]
+    pub live_interval_ms: u64,
+
+    /// Timeout for a single liveness probe attempt (ms)
+    #[arg(long, default_value = "1000")]
+    pub live_timeout_ms: u64,
+
+    /// Consecutive liveness probe failures before the process is restarted
+    #[arg(long, default_value = "3")]
+    pub live_failure_threshold: u32,
+
+    /// Grace period after spawn before liveness probing starts (seconds)
+    #[arg(long, default_value = "0")]
+    pub live_start_period_secs: u64,
+
+    /// Offer an sd_notify socket (NOTIFY_SOCKET); the process counts as ready once it sends READY=1
+    #[arg(long)]
+    pub notify: bool,
+
+    /// Address of the notify socket: a path, or @NAME for an abstract socket (implies --notify)
+    #[arg(long, value_name = "ADDR")]
+    pub notify_socket: Option<NotifyAddress>,
+
+    /// Restart the process if it doesn't send WATCHDOG=1 within this interval (seconds, implies --notify)
+    #[arg(long)]
+    pub watchdog_secs: Option<u64>,
+
     /// Graceful shutdown timeout (seconds)
     #[arg(long, default_value = "30")]
     pub graceful_timeout_secs: u64,
 
-    /// Signal polling interval (ms)
-    #[arg(long, default_value = "100")]
-    pub signal_poll_interval_ms: u64,
-
     /// Zombie reaping interval (ms)
     #[arg(long, default_value = "5000")]
     pub zombie_reap_interval_ms: u64,
 
-    /// Command to execute
-    pub command: String,
+    /// Don't register as a child subreaper when not running as PID 1
+    #[arg(long)]
+    pub no_subreaper: bool,
+
+    /// Remap a child exit code before scinit exits with it (FROM=TO, repeatable); FROM may be `oom` for OOM kills
+    /// (only detected with --cgroup) or `exhausted` for scinit giving up after the restart budget ran out (default 122)
+    #[arg(long = "remap-exit", value_name = "FROM=TO", value_parser = parse_exit_code_mapping)]
+    pub remap_exit: Vec<(ExitMatch, i32)>,
+
+    /// Rewrite a received signal before forwarding it: FROM=TO, where TO may be `none` to not forward it (repeatable)
+    #[arg(long = "rewrite", value_name = "FROM=TO", value_parser = parse_signal_rewrite)]
+    pub rewrites: Vec<(i32, Option<i32>)>,
+
+    /// Signal that stops services gracefully (default: SIGTERM, or what --rewrite maps it to)
+    #[arg(long, value_name = "SIGNAL", value_parser = parse_signal)]
+    pub stop_signal: Option<Signal>,
+
+    /// Deliver a forwarded signal to the whole process group or only the command: SIGNAL=group|child (repeatable)
+    #[arg(long = "signal-target", value_name = "SIGNAL=TARGET", value_parser = parse_signal_target)]
+    pub signal_targets: Vec<(i32, SignalTarget)>,
+
+    /// Restart the services when scinit receives this signal, e.g. SIGHUP, instead of forwarding it (repeatable)
+    #[arg(long = "restart-signal", value_name = "SIGNAL", value_parser = parse_signal)]
+    pub restart_signals: Vec<Signal>,
+
+    /// How services are replaced on a restart signal (default: --restart-mode)
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub restart_signal_mode: Option<RestartMode>,
+
+    /// Stop scinit itself after forwarding SIGTSTP, freezing restarts and probes until SIGCONT
+    #[arg(long)]
+    pub job_control: bool,
+
+    /// When to restart the command after it exits on its own
+    #[arg(long, value_enum, default_value = "never")]
+    pub restart: RestartPolicy,
+
+    /// Initial delay before a crash restart, doubled on each restart in the window (ms)
+    #[arg(long, default_value = "1000")]
+    pub restart_backoff_ms: u64,
+
+    /// Maximum delay before a crash restart (ms)
+    #[arg(long, default_value = "30000")]
+    pub restart_max_backoff_ms: u64,
+
+    /// Random spread applied to restart delays, as a fraction of the delay
+    #[arg(long, default_value = "0.1")]
+    pub restart_jitter: f64,
+
+    /// Maximum number of crash restarts within the restart window
+    #[arg(long, default_value = "5")]
+    pub restart_max_attempts: u32,
+
+    /// Window the restart budget applies to (seconds)
+    #[arg(long, default_value = "60")]
+    pub restart_window_secs: u64,
+
+    /// Additional service to supervise next to the command: NAME=COMMAND [ARGS...] (repeatable)
+    #[arg(long = "service", value_name = "NAME=COMMAND")]
+    pub services: Vec<ServiceSpec>,
+
+    /// What happens when a service exits and is not restarted by the restart policy
+    #[arg(long, value_enum, default_value = "exit-all")]
+    pub on_service_exit: ServiceExitPolicy,
+
+    /// Working directory for the command
+    #[arg(long, value_name = "DIR")]
+    pub working_directory: Option<PathBuf>,
+
+    /// Environment variable for the command: KEY=VALUE, or KEY to pass on scinit's value (repeatable)
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_assignment)]
+    pub env: Vec<(String, String)>,
+
+    /// Dotenv-style file of environment variables, reloaded on change during live-reload (repeatable)
+    #[arg(long = "env-file", value_name = "PATH")]
+    pub env_files: Vec<PathBuf>,
+
+    /// Don't pass scinit's own environment on to the command
+    #[arg(long)]
+    pub env_clear: bool,
+
+    /// Pass on only inherited variables matching NAME or PREFIX_* (repeatable)
+    #[arg(long = "env-allow", value_name = "PATTERN")]
+    pub env_allow: Vec<String>,
+
+    /// Run the command as this user (name or UID), with its groups from /etc/group
+    #[arg(long, value_name = "USER")]
+    pub user: Option<String>,
+
+    /// Run the command with this primary group (name or GID)
+    #[arg(long, value_name = "GROUP")]
+    pub group: Option<String>,
+
+    /// Resource limit for the command: NAME=SOFT[:HARD], e.g. nofile=65536 (repeatable)
+    #[arg(long = "rlimit", value_name = "NAME=SOFT[:HARD]")]
+    pub rlimits: Vec<RlimitSpec>,
+
+    /// Place each service's process tree in its own cgroup v2, killed as a whole on stop
+    #[arg(long)]
+    pub cgroup: bool,
+
+    /// Delegated cgroup to create service cgroups in (default: scinit's own cgroup)
+    #[arg(long, value_name = "DIR")]
+    pub cgroup_root: Option<PathBuf>,
+
+    /// Memory limit per service, e.g. 512M (implies --cgroup)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub memory_max: Option<u64>,
+
+    /// CPU limit per service in CPUs, e.g. 1.5 (implies --cgroup)
+    #[arg(long, value_name = "CPUS")]
+    pub cpu_max: Option<CpuMax>,
+
+    /// Maximum number of processes per service (implies --cgroup)
+    #[arg(long, value_name = "N")]
+    pub pids_max: Option<u64>,
+
+    #[command(subcommand)]
+    pub action: Option<Action>,
+
+    /// Command to execute; put `--` before a command named like a subcommand
+    pub command: Option<String>,
 
     /// Arguments for the command
     pub args: Vec<String>,
 }
 
+/// Subcommands
+///
+/// A subcommand must come first and takes no flags but `--config`, as in
+/// `scinit check-config --config scinit.toml`.
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Validate the configuration file and print the effective settings
+    CheckConfig,
+}
+
 /// Configuration for the init system
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -65,73 +298,309 @@ pub struct Config {
     pub command: String,
     /// Arguments for the command
     pub args: Vec<String>,
-    /// Signal polling interval in milliseconds (optimized for performance)
-    pub signal_poll_interval: Duration,
+    /// Working directory for the command
+    pub working_directory: Option<PathBuf>,
+    /// Inherited environment filtering and env files
+    pub env: EnvConfig,
+    /// Extra environment variables for the command
+    pub environment: HashMap<String, String>,
+    /// Identity the command runs as, if not scinit's own
+    pub credentials: Option<Credentials>,
+    /// Resource limits for the command
+    pub rlimits: Vec<RlimitSpec>,
+    /// cgroup limits for each service; the path is filled in per service
+    pub cgroup: Option<CgroupConfig>,
+    /// Delegated cgroup to create service cgroups in, instead of scinit's own
+    pub cgroup_root: Option<PathBuf>,
+    /// Rewriting and delivery of forwarded signals
+    pub signal_policy: SignalPolicy,
+    /// Signal that stops services gracefully
+    pub stop_signal: Signal,
+    /// Where the stop signal is delivered
+    pub stop_target: SignalTarget,
     /// Zombie reaping interval in milliseconds
     pub zombie_reap_interval: Duration,
+    /// Whether to register as a child subreaper when not PID 1
+    pub subreaper: bool,
     /// Live-reload configuration
     pub live_reload: LiveReloadConfig,
     /// Port binding configuration
     pub port_binding: PortBindingConfig,
+    /// Crash restart policy
+    pub restart_policy: RestartPolicyConfig,
+    /// Readiness detection for the managed process
+    pub readiness: Option<ReadinessConfig>,
+    /// Liveness checking for the managed process
+    pub liveness: Option<LivenessConfig>,
+    /// sd_notify socket offered to the managed process
+    pub notify: Option<NotifyConfig>,
+    /// Exit code remapping applied to the child's exit code
+    pub exit_code_remap: HashMap<ExitMatch, i32>,
+    /// Additional services supervised next to the command
+    pub services: Vec<ServiceSpec>,
+    /// What happens when a service exits and is not restarted
+    pub service_exit_policy: ServiceExitPolicy,
 }
 
 #[derive(Debug, Clone)]
 pub struct LiveReloadConfig {
     pub enabled: bool,
-    pub watch_path: Option<PathBuf>,
+    pub watch_paths: Vec<PathBuf>,
+    pub recursive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub use_ignore_files: bool,
+    pub backend: WatchBackend,
+    pub poll_interval_ms: u64,
+    pub on_change_run: Option<String>,
     pub debounce_ms: u64,
     pub restart_delay_ms: u64,
+    pub restart_mode: RestartMode,
     pub graceful_timeout_secs: u64,
 }
 
 impl Config {
+    /// Builds the configuration from the command line and the configuration file it names
+    ///
+    /// # Arguments
+    /// * `cli` - Parsed command line
+    /// * `matches` - Matches `cli` was parsed from, used to let explicit flags override the file
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The effective configuration or an error
+    pub fn load(mut cli: Cli, matches: &ArgMatches) -> Result<Self> {
+        let file_environment = match cli.config {
+            Some(ref path) => {
+                let mut file = FileConfig::load(path)?;
+                // [env] has no flag to fill in, so it joins the configuration directly
+                let environment = std::mem::take(&mut file.env);
+                file.apply_to(&mut cli, matches)?;
+                environment
+            }
+            None => HashMap::new(),
+        };
+
+        let mut config = Self::from_cli(cli)?;
+        // --env takes precedence over the configuration file's [env]
+        config.environment = file_environment.into_iter().chain(config.environment).collect();
+        Ok(config)
+    }
+
     /// Parse command line arguments into configuration
     pub fn from_cli(cli: Cli) -> Result<Self> {
+        let command = cli
+            .command
+            .ok_or_else(|| eyre!("No command given: pass one on the command line or set `command` in the config file"))?;
+
         // Parse bind address
         let bind_address: IpAddr = cli
             .bind_addr
             .parse()
             .map_err(|e| eyre!("Invalid bind address '{}': {}", cli.bind_addr, e))?;
 
-        // Determine watch path
-        let watch_path = cli.watch_path.or_else(|| {
-            if cli.live_reload {
-                Some(PathBuf::from(&cli.command))
-            } else {
-                None
+        // Determine watch paths: the sources a build command compiles, otherwise the executable
+        let watch_paths = if cli.watch_paths.is_empty() && cli.live_reload {
+            match (&cli.on_change_run, &cli.working_directory) {
+                (Some(_), Some(dir)) => vec![dir.clone()],
+                (Some(_), None) => vec![PathBuf::from(".")],
+                (None, _) => vec![PathBuf::from(&command)],
             }
+        } else {
+            cli.watch_paths
+        };
+        build_globset(&cli.include)?;
+        build_globset(&cli.exclude)?;
+
+        if !(0.0..=1.0).contains(&cli.restart_jitter) {
+            return Err(eyre!("Invalid restart jitter {}: expected a fraction between 0 and 1", cli.restart_jitter));
+        }
+        if cli.live_failure_threshold == 0 {
+            return Err(eyre!("Invalid liveness failure threshold: expected at least 1"));
+        }
+        if let Some(service) = cli.services.iter().find(|service| service.name == PRIMARY_SERVICE) {
+            return Err(eyre!("Invalid service '{}': the name '{}' is reserved for the command", service, PRIMARY_SERVICE));
+        }
+
+        for rlimit in cli.rlimits.iter().chain(cli.services.iter().flat_map(|service| service.rlimits.iter().flatten())) {
+            rlimit.validate()?;
+        }
+
+        if cli.watchdog_secs == Some(0) {
+            return Err(eyre!("Invalid watchdog interval: expected at least 1 second"));
+        }
+
+        let signal_policy = SignalPolicy {
+            rewrites: cli.rewrites.into_iter().collect(),
+            targets: cli.signal_targets.into_iter().collect(),
+            restart_signals: cli.restart_signals,
+            restart_mode: cli.restart_signal_mode.unwrap_or(cli.restart_mode),
+            job_control: cli.job_control,
+        };
+        let stop_signal = match cli.stop_signal {
+            Some(signal) => signal,
+            None => match signal_policy.rewrite(Signal::SIGTERM as i32).map(Signal::try_from) {
+                Some(Ok(signal)) => signal,
+                Some(Err(_)) => {
+                    return Err(eyre!("Invalid rewrite of SIGTERM: a real-time signal can't be the stop signal, set --stop-signal"))
+                }
+                None => Signal::SIGTERM,
+            },
+        };
+        let stop_target = signal_policy.target(stop_signal as i32);
+
+        let notify = (cli.notify || cli.notify_socket.is_some() || cli.watchdog_secs.is_some()).then(|| NotifyConfig {
+            address: cli.notify_socket.clone().unwrap_or_else(NotifyAddress::default_for_current_process),
+            watchdog: cli.watchdog_secs.map(Duration::from_secs),
         });
 
         Ok(Config {
-            command: cli.command,
+            command,
             args: cli.args,
-            signal_poll_interval: Duration::from_millis(cli.signal_poll_interval_ms),
+            working_directory: cli.working_directory,
+            env: EnvConfig {
+                clear: cli.env_clear,
+                allow: cli.env_allow,
+                files: cli.env_files,
+            },
+            environment: cli.env.into_iter().collect(),
+            credentials: Credentials::resolve(cli.user.as_deref(), cli.group.as_deref())?,
+            rlimits: cli.rlimits,
+            cgroup: (cli.cgroup
+                || cli.cgroup_root.is_some()
+                || cli.memory_max.is_some()
+                || cli.cpu_max.is_some()
+                || cli.pids_max.is_some())
+            .then(|| CgroupConfig {
+                path: PathBuf::new(),
+                memory_max: cli.memory_max,
+                cpu_max: cli.cpu_max,
+                pids_max: cli.pids_max,
+            }),
+            cgroup_root: cli.cgroup_root,
+            signal_policy,
+            stop_signal,
+            stop_target,
             zombie_reap_interval: Duration::from_millis(cli.zombie_reap_interval_ms),
+            subreaper: !cli.no_subreaper,
             live_reload: LiveReloadConfig {
                 enabled: cli.live_reload,
-                watch_path,
+                watch_paths,
+                recursive: cli.recursive,
+                include: cli.include,
+                exclude: cli.exclude,
+                use_ignore_files: !cli.no_ignore,
+                backend: cli.watch_backend,
+                poll_interval_ms: cli.watch_poll_interval_ms,
+                on_change_run: cli.on_change_run,
                 debounce_ms: cli.debounce_ms,
                 restart_delay_ms: cli.restart_delay_ms,
+                restart_mode: cli.restart_mode,
                 graceful_timeout_secs: cli.graceful_timeout_secs,
             },
             port_binding: PortBindingConfig {
                 ports: cli.ports,
                 bind_address,
+                listeners: cli.listeners,
                 reuse_port: true,
+                socket_activation: cli.socket_activation,
             },
+            restart_policy: RestartPolicyConfig {
+                policy: cli.restart,
+                initial_backoff: Duration::from_millis(cli.restart_backoff_ms),
+                max_backoff: Duration::from_millis(cli.restart_max_backoff_ms),
+                jitter: cli.restart_jitter,
+                max_restarts: cli.restart_max_attempts,
+                window: Duration::from_secs(cli.restart_window_secs),
+            },
+            readiness: (cli.ready_probe.is_some() || notify.is_some()).then(|| ReadinessConfig {
+                probe: cli.ready_probe,
+                interval: Duration::from_millis(cli.ready_interval_ms),
+                timeout: Duration::from_millis(cli.ready_timeout_ms),
+                startup_timeout: Duration::from_secs(cli.ready_startup_timeout_secs),
+            }),
+            liveness: cli.live_probe.map(|probe| LivenessConfig {
+                probe,
+                interval: Duration::from_millis(cli.live_interval_ms),
+                timeout: Duration::from_millis(cli.live_timeout_ms),
+                failure_threshold: cli.live_failure_threshold,
+                start_period: Duration::from_secs(cli.live_start_period_secs),
+            }),
+            notify,
+            exit_code_remap: cli.remap_exit.into_iter().collect(),
+            services: cli.services,
+            service_exit_policy: cli.on_service_exit,
         })
     }
 
+    /// Applies the configured exit code remapping
+    ///
+    /// An `oom` mapping takes precedence for children killed by the OOM killer.
+    pub fn map_exit_code(&self, code: i32, oom_killed: bool) -> i32 {
+        oom_killed
+            .then(|| self.exit_code_remap.get(&ExitMatch::OomKill))
+            .flatten()
+            .or_else(|| self.exit_code_remap.get(&ExitMatch::Code(code)))
+            .copied()
+            .unwrap_or(code)
+    }
+
+    /// Exit code for scinit giving up after the restart budget ran out, after remapping
+    pub fn exhausted_exit_code(&self) -> i32 {
+        self.exit_code_remap
+            .get(&ExitMatch::RestartsExhausted)
+            .copied()
+            .unwrap_or(EXIT_CODE_RESTARTS_EXHAUSTED)
+    }
+
     /// Get file watch configuration if live-reload is enabled
     pub fn file_watch_config(&self) -> Option<FileWatchConfig> {
-        if self.live_reload.enabled {
-            self.live_reload.watch_path.as_ref().map(|path| FileWatchConfig {
-                watch_path: path.clone(),
-                debounce_ms: self.live_reload.debounce_ms,
-                recursive: false,
-            })
-        } else {
-            None
-        }
+        (self.live_reload.enabled && !self.live_reload.watch_paths.is_empty()).then(|| FileWatchConfig {
+            watch_paths: self.live_reload.watch_paths.clone(),
+            debounce_ms: self.live_reload.debounce_ms,
+            recursive: self.live_reload.recursive,
+            include: self.live_reload.include.clone(),
+            exclude: self.live_reload.exclude.clone(),
+            use_ignore_files: self.live_reload.use_ignore_files,
+            extra_paths: self.env.files.clone(),
+            executable: resolve_executable(&self.command),
+            backend: self.live_reload.backend,
+            poll_interval_ms: self.live_reload.poll_interval_ms,
+            on_change_run: self.live_reload.on_change_run.clone(),
+            working_directory: self.working_directory.clone(),
+        })
     }
-}
\ No newline at end of file
+}
+
+/// Which child exits an exit code mapping applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitMatch {
+    /// Exits with this code, including `128 + signal` for deaths by signal
+    Code(i32),
+    /// Kills by the OOM killer
+    OomKill,
+    /// scinit giving up because the restart budget ran out
+    RestartsExhausted,
+}
+
+/// Parses an exit code mapping of the form `FROM=TO`, where FROM may be `oom` or `exhausted`
+pub(crate) fn parse_exit_code_mapping(s: &str) -> Result<(ExitMatch, i32)> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| eyre!("Invalid exit code mapping '{}': expected FROM=TO", s))?;
+    let parse = |code: &str| -> Result<i32> {
+        let code: i32 = code
+            .trim()
+            .parse()
+            .map_err(|e| eyre!("Invalid exit code '{}' in '{}': {}", code, s, e))?;
+        if !(0..=255).contains(&code) {
+            return Err(eyre!("Exit code {} in '{}' is out of range 0-255", code, s));
+        }
+        Ok(code)
+    };
+    let from = match from.trim() {
+        "oom" => ExitMatch::OomKill,
+        "exhausted" => ExitMatch::RestartsExhausted,
+        code => ExitMatch::Code(parse(code)?),
+    };
+    Ok((from, parse(to)?))
+}
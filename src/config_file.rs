@@ -0,0 +1,623 @@
+use super::Result;
+use crate::cgroups::parse_size;
+use crate::cli::{parse_exit_code_mapping, Cli};
+use crate::signals::{parse_signal, parse_signal_rewrite, parse_signal_target};
+use crate::supervisor::ServiceSpec;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use eyre::eyre;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Contents of a `scinit.toml` configuration file
+///
+/// Every setting mirrors a command line flag and uses the same units and
+/// value syntax. Flags given on the command line take precedence.
+///
+/// ```toml
+/// command = "/usr/local/bin/app"
+/// args = ["--port", "8080"]
+/// working_directory = "/srv/app"
+/// env_files = ["/srv/app/.env"]
+///
+/// [env]
+/// RUST_LOG = "info"
+///
+/// [network]
+/// ports = [8080]
+///
+/// [restart]
+/// policy = "on-failure"
+///
+/// [[service]]
+/// name = "logs"
+/// command = "vector"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// Command to execute
+    pub command: Option<String>,
+    /// Arguments for the command
+    pub args: Option<Vec<String>>,
+    /// Working directory of the command
+    pub working_directory: Option<PathBuf>,
+    /// Extra environment variables for the command
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Dotenv-style files of environment variables
+    pub env_files: Option<Vec<PathBuf>>,
+    /// Whether to withhold scinit's own environment from the command
+    pub env_clear: Option<bool>,
+    /// Inherited variables to pass on, as NAME or PREFIX_*
+    pub env_allow: Option<Vec<String>>,
+    /// User the command runs as
+    pub user: Option<String>,
+    /// Primary group the command runs with
+    pub group: Option<String>,
+    /// Resource limits as `NAME=SOFT[:HARD]`
+    pub rlimit: Option<Vec<String>>,
+    /// Whether to register as a child subreaper when not PID 1
+    pub subreaper: Option<bool>,
+    /// Zombie reaping interval (ms)
+    pub zombie_reap_interval_ms: Option<u64>,
+    /// Graceful shutdown timeout (seconds)
+    pub graceful_timeout_secs: Option<u64>,
+    /// Exit code remappings as `FROM=TO`
+    pub remap_exit: Option<Vec<String>>,
+    /// What happens when a service exits and is not restarted
+    pub on_service_exit: Option<String>,
+    /// Signal rewriting and delivery
+    #[serde(default)]
+    pub signals: SignalsSection,
+    /// Ports and listeners
+    #[serde(default)]
+    pub network: NetworkSection,
+    /// Live reload
+    #[serde(default)]
+    pub watch: WatchSection,
+    /// Restart policy and restart behaviour
+    #[serde(default)]
+    pub restart: RestartSection,
+    /// Readiness probe
+    #[serde(default)]
+    pub ready: ReadySection,
+    /// Liveness probe
+    #[serde(default)]
+    pub liveness: LivenessSection,
+    /// sd_notify socket
+    #[serde(default)]
+    pub notify: NotifySection,
+    /// cgroup placement and limits
+    #[serde(default)]
+    pub cgroup: CgroupSection,
+    /// Additional services
+    #[serde(default, rename = "service")]
+    pub services: Vec<FileService>,
+}
+
+/// `[signals]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignalsSection {
+    pub rewrite: Option<Vec<String>>,
+    pub stop_signal: Option<String>,
+    pub target: Option<Vec<String>>,
+    pub restart: Option<Vec<String>>,
+    pub restart_mode: Option<String>,
+    pub job_control: Option<bool>,
+}
+
+/// `[network]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkSection {
+    pub ports: Option<Vec<u16>>,
+    pub bind_addr: Option<String>,
+    pub listen: Option<Vec<String>>,
+    pub socket_activation: Option<bool>,
+}
+
+/// `[watch]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchSection {
+    pub enabled: Option<bool>,
+    pub path: Option<PathBuf>,
+    pub paths: Option<Vec<PathBuf>>,
+    pub recursive: Option<bool>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub ignore_files: Option<bool>,
+    pub backend: Option<String>,
+    pub poll_interval_ms: Option<u64>,
+    pub on_change_run: Option<String>,
+    pub debounce_ms: Option<u64>,
+}
+
+/// `[restart]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RestartSection {
+    pub policy: Option<String>,
+    pub mode: Option<String>,
+    pub delay_ms: Option<u64>,
+    pub backoff_ms: Option<u64>,
+    pub max_backoff_ms: Option<u64>,
+    pub jitter: Option<f64>,
+    pub max_attempts: Option<u32>,
+    pub window_secs: Option<u64>,
+}
+
+/// `[ready]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReadySection {
+    pub probe: Option<String>,
+    pub interval_ms: Option<u64>,
+    pub timeout_ms: Option<u64>,
+    pub startup_timeout_secs: Option<u64>,
+}
+
+/// `[liveness]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LivenessSection {
+    pub probe: Option<String>,
+    pub interval_ms: Option<u64>,
+    pub timeout_ms: Option<u64>,
+    pub failure_threshold: Option<u32>,
+    pub start_period_secs: Option<u64>,
+}
+
+/// `[notify]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifySection {
+    pub enabled: Option<bool>,
+    pub socket: Option<String>,
+    pub watchdog_secs: Option<u64>,
+}
+
+/// `[cgroup]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupSection {
+    pub enabled: Option<bool>,
+    pub root: Option<PathBuf>,
+    pub memory_max: Option<String>,
+    pub cpu_max: Option<f64>,
+    pub pids_max: Option<u64>,
+}
+
+/// `[[service]]` table
+///
+/// Settings left unset fall back to the main command's as described on [`ServiceSpec`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileService {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub working_directory: Option<PathBuf>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub ports: Vec<u16>,
+    #[serde(default)]
+    pub listen: Vec<String>,
+    pub restart: Option<String>,
+    pub restart_mode: Option<String>,
+    pub on_exit: Option<String>,
+    pub user: Option<String>,
+    pub group: Option<String>,
+    pub rlimit: Option<Vec<String>>,
+    pub stop_signal: Option<String>,
+    pub env_files: Option<Vec<PathBuf>>,
+}
+
+impl FileConfig {
+    /// Reads and parses a configuration file
+    ///
+    /// Relative paths in the file are resolved against the file's directory.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the TOML file
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The parsed file or an error naming the file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read config file {}: {}", path.display(), e))?;
+        let mut file: Self =
+            toml::from_str(&contents).map_err(|e| eyre!("Invalid config file {}: {}", path.display(), e))?;
+        file.resolve_paths(path.parent().unwrap_or(Path::new("")));
+        Ok(file)
+    }
+
+    /// Makes relative paths relative to the given directory
+    fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        };
+
+        self.working_directory.iter_mut().for_each(resolve);
+        self.env_files.iter_mut().flatten().for_each(resolve);
+        self.watch.path.iter_mut().for_each(resolve);
+        self.watch.paths.iter_mut().flatten().for_each(resolve);
+        for service in &mut self.services {
+            service.working_directory.iter_mut().for_each(resolve);
+            service.env_files.iter_mut().flatten().for_each(resolve);
+        }
+    }
+
+    /// Fills in every flag that wasn't given on the command line
+    ///
+    /// # Arguments
+    /// * `cli` - Parsed command line, updated in place
+    /// * `matches` - Matches `cli` was parsed from, used to tell explicit flags from defaults
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success, or an error for an invalid value in the file
+    pub fn apply_to(self, cli: &mut Cli, matches: &ArgMatches) -> Result<()> {
+        let explicit = |id: &str| {
+            matches!(matches.value_source(id), Some(source) if source != ValueSource::DefaultValue)
+        };
+
+        // Takes the file's value unless the flag was given explicitly
+        macro_rules! merge {
+            ($field:ident, $value:expr) => {
+                if let Some(value) = $value {
+                    if !explicit(stringify!($field)) {
+                        cli.$field = value;
+                    }
+                }
+            };
+        }
+
+        // The file's arguments belong to the file's command
+        if !explicit("command") {
+            merge!(command, self.command.map(Some));
+            merge!(args, self.args);
+        }
+        merge!(no_subreaper, self.subreaper.map(|subreaper| !subreaper));
+        merge!(zombie_reap_interval_ms, self.zombie_reap_interval_ms);
+        merge!(graceful_timeout_secs, self.graceful_timeout_secs);
+        merge!(remap_exit, parse_list(self.remap_exit, parse_exit_code_mapping)?);
+        merge!(on_service_exit, parse_value_enum(self.on_service_exit)?);
+
+        merge!(rewrites, parse_list(self.signals.rewrite, parse_signal_rewrite)?);
+        merge!(stop_signal, self.signals.stop_signal.as_deref().map(parse_signal).transpose()?.map(Some));
+        merge!(signal_targets, parse_list(self.signals.target, parse_signal_target)?);
+        merge!(restart_signals, parse_list(self.signals.restart, parse_signal)?);
+        merge!(restart_signal_mode, parse_value_enum(self.signals.restart_mode)?.map(Some));
+        merge!(job_control, self.signals.job_control);
+
+        merge!(ports, self.network.ports);
+        merge!(bind_addr, self.network.bind_addr);
+        merge!(listeners, parse_list(self.network.listen, str::parse)?);
+        merge!(socket_activation, self.network.socket_activation);
+
+        merge!(live_reload, self.watch.enabled);
+        merge!(watch_paths, self.watch.paths.or(self.watch.path.map(|path| vec![path])));
+        merge!(recursive, self.watch.recursive);
+        merge!(include, self.watch.include);
+        merge!(exclude, self.watch.exclude);
+        merge!(no_ignore, self.watch.ignore_files.map(|ignore_files| !ignore_files));
+        merge!(watch_backend, parse_value_enum(self.watch.backend)?);
+        merge!(watch_poll_interval_ms, self.watch.poll_interval_ms);
+        merge!(on_change_run, self.watch.on_change_run.map(Some));
+        merge!(debounce_ms, self.watch.debounce_ms);
+
+        merge!(restart, parse_value_enum(self.restart.policy)?);
+        merge!(restart_mode, parse_value_enum(self.restart.mode)?);
+        merge!(restart_delay_ms, self.restart.delay_ms);
+        merge!(restart_backoff_ms, self.restart.backoff_ms);
+        merge!(restart_max_backoff_ms, self.restart.max_backoff_ms);
+        merge!(restart_jitter, self.restart.jitter);
+        merge!(restart_max_attempts, self.restart.max_attempts);
+        merge!(restart_window_secs, self.restart.window_secs);
+
+        merge!(ready_probe, parse_value(self.ready.probe)?.map(Some));
+        merge!(ready_interval_ms, self.ready.interval_ms);
+        merge!(ready_timeout_ms, self.ready.timeout_ms);
+        merge!(ready_startup_timeout_secs, self.ready.startup_timeout_secs);
+
+        merge!(live_probe, parse_value(self.liveness.probe)?.map(Some));
+        merge!(live_interval_ms, self.liveness.interval_ms);
+        merge!(live_timeout_ms, self.liveness.timeout_ms);
+        merge!(live_failure_threshold, self.liveness.failure_threshold);
+        merge!(live_start_period_secs, self.liveness.start_period_secs);
+
+        merge!(notify, self.notify.enabled);
+        merge!(notify_socket, parse_value(self.notify.socket)?.map(Some));
+        merge!(watchdog_secs, self.notify.watchdog_secs.map(Some));
+
+        merge!(cgroup, self.cgroup.enabled);
+        merge!(cgroup_root, self.cgroup.root.map(Some));
+        merge!(memory_max, self.cgroup.memory_max.as_deref().map(parse_size).transpose()?.map(Some));
+        merge!(cpu_max, parse_value(self.cgroup.cpu_max.map(|cpus| cpus.to_string()))?.map(Some));
+        merge!(pids_max, self.cgroup.pids_max.map(Some));
+
+        let services = self
+            .services
+            .into_iter()
+            .map(FileService::into_spec)
+            .collect::<Result<Vec<_>>>()?;
+        merge!(services, (!services.is_empty()).then_some(services));
+
+        merge!(working_directory, self.working_directory.map(Some));
+        merge!(env_files, self.env_files);
+        merge!(env_clear, self.env_clear);
+        merge!(env_allow, self.env_allow);
+        merge!(user, self.user.map(Some));
+        merge!(group, self.group.map(Some));
+        merge!(rlimits, parse_list(self.rlimit, str::parse)?);
+        Ok(())
+    }
+}
+
+impl FileService {
+    /// Converts the table into a service spec, parsing its values
+    fn into_spec(self) -> Result<ServiceSpec> {
+        let context = |e: eyre::Report| eyre!("Invalid service '{}': {}", self.name, e);
+        Ok(ServiceSpec {
+            listeners: parse_list(Some(self.listen.clone()), str::parse).map_err(context)?.unwrap_or_default(),
+            restart: parse_value_enum(self.restart.clone()).map_err(context)?,
+            restart_mode: parse_value_enum(self.restart_mode.clone()).map_err(context)?,
+            on_exit: parse_value_enum(self.on_exit.clone()).map_err(context)?,
+            rlimits: parse_list(self.rlimit.clone(), str::parse).map_err(context)?,
+            stop_signal: self.stop_signal.as_deref().map(parse_signal).transpose().map_err(context)?,
+            name: self.name,
+            command: self.command,
+            args: self.args,
+            working_directory: self.working_directory,
+            environment: self.env,
+            ports: self.ports,
+            user: self.user,
+            group: self.group,
+            env_files: self.env_files,
+        })
+    }
+}
+
+/// Parses an optional string with `FromStr`
+fn parse_value<T: FromStr<Err = eyre::Report>>(value: Option<String>) -> Result<Option<T>> {
+    value.map(|value| value.parse()).transpose()
+}
+
+/// Parses an optional list of strings with the given parser
+fn parse_list<T>(values: Option<Vec<String>>, parse: impl Fn(&str) -> Result<T>) -> Result<Option<Vec<T>>> {
+    values
+        .map(|values| values.iter().map(|value| parse(value)).collect())
+        .transpose()
+}
+
+/// Parses an optional string as a clap value enum, accepting the same names as the command line
+fn parse_value_enum<T: ValueEnum>(value: Option<String>) -> Result<Option<T>> {
+    value
+        .map(|value| {
+            T::from_str(&value, false).map_err(|_| {
+                let expected: Vec<_> = T::value_variants()
+                    .iter()
+                    .filter_map(|variant| variant.to_possible_value())
+                    .map(|value| value.get_name().to_string())
+                    .collect();
+                eyre!("Invalid value '{}': expected one of {}", value, expected.join(", "))
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Action, Config, ExitMatch};
+    use crate::file_watcher::WatchBackend;
+    use crate::process_manager::RestartMode;
+    use crate::restart_policy::RestartPolicy;
+    use crate::signals::{Signal, SignalTarget};
+    use crate::supervisor::ServiceExitPolicy;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const EXAMPLE: &str = r#"
+        command = "/usr/local/bin/app"
+        args = ["--port", "8080"]
+        working_directory = "/srv/app"
+        remap_exit = ["143=0", "oom=3", "exhausted=70"]
+        env_files = [".env"]
+        env_allow = ["PATH", "APP_*"]
+        rlimit = ["core=0"]
+
+        [env]
+        RUST_LOG = "info"
+
+        [signals]
+        rewrite = ["SIGTERM=SIGQUIT", "USR2=none"]
+        target = ["HUP=child"]
+        restart = ["SIGHUP"]
+
+        [network]
+        ports = [8080, 8081]
+        listen = ["unix:///run/app.sock"]
+
+        [watch]
+        paths = ["src", "Cargo.toml"]
+        recursive = true
+        exclude = ["target/**"]
+        backend = "poll"
+        on_change_run = "cargo build"
+
+        [restart]
+        policy = "on-failure"
+        mode = "overlap"
+        max_attempts = 3
+
+        [ready]
+        probe = "tcp://127.0.0.1:8080"
+
+        [[service]]
+        name = "logs"
+        command = "vector"
+        args = ["--config", "/etc/vector.toml"]
+        restart = "always"
+        on_exit = "ignore"
+        user = "nobody"
+        rlimit = ["nofile=1024"]
+        stop_signal = "SIGINT"
+        env_files = ["/etc/vector.env"]
+    "#;
+
+    fn parse_cli(args: &[&str]) -> (Cli, ArgMatches) {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        (Cli::from_arg_matches(&matches).unwrap(), matches)
+    }
+
+    #[test]
+    fn test_file_values_fill_in_defaults() {
+        let file: FileConfig = toml::from_str(EXAMPLE).unwrap();
+        let (mut cli, matches) = parse_cli(&["scinit"]);
+        file.apply_to(&mut cli, &matches).unwrap();
+
+        assert_eq!(cli.command.as_deref(), Some("/usr/local/bin/app"));
+        assert_eq!(cli.args, vec!["--port", "8080"]);
+        assert_eq!(cli.working_directory, Some(PathBuf::from("/srv/app")));
+        assert_eq!(cli.env_files, vec![PathBuf::from(".env")]);
+        assert_eq!(cli.env_allow, vec!["PATH", "APP_*"]);
+        assert_eq!(cli.rlimits.len(), 1);
+        assert_eq!(
+            cli.remap_exit,
+            vec![(ExitMatch::Code(143), 0), (ExitMatch::OomKill, 3), (ExitMatch::RestartsExhausted, 70)]
+        );
+        assert_eq!(cli.rewrites, vec![(libc::SIGTERM, Some(libc::SIGQUIT)), (libc::SIGUSR2, None)]);
+        assert_eq!(cli.signal_targets, vec![(libc::SIGHUP, SignalTarget::Child)]);
+        assert_eq!(cli.restart_signals, vec![Signal::SIGHUP]);
+        assert_eq!(cli.watch_paths, vec![PathBuf::from("src"), PathBuf::from("Cargo.toml")]);
+        assert!(cli.recursive);
+        assert_eq!(cli.exclude, vec!["target/**"]);
+        assert_eq!(cli.watch_backend, WatchBackend::Poll);
+        assert_eq!(cli.on_change_run.as_deref(), Some("cargo build"));
+        assert_eq!(cli.ports, vec![8080, 8081]);
+        assert_eq!(cli.listeners.len(), 1);
+        assert_eq!(cli.restart, RestartPolicy::OnFailure);
+        assert_eq!(cli.restart_mode, RestartMode::Overlap);
+        assert_eq!(cli.restart_max_attempts, 3);
+        assert!(cli.ready_probe.is_some());
+
+        assert_eq!(cli.services.len(), 1);
+        assert_eq!(cli.services[0].name, "logs");
+        assert_eq!(cli.services[0].restart, Some(RestartPolicy::Always));
+        assert_eq!(cli.services[0].on_exit, Some(ServiceExitPolicy::Ignore));
+        assert_eq!(cli.services[0].restart_mode, None);
+        assert_eq!(cli.services[0].user.as_deref(), Some("nobody"));
+        assert_eq!(cli.services[0].group, None);
+        assert_eq!(cli.services[0].rlimits.as_ref().map(Vec::len), Some(1));
+        assert_eq!(cli.services[0].stop_signal, Some(Signal::SIGINT));
+        assert_eq!(cli.services[0].env_files, Some(vec![PathBuf::from("/etc/vector.env")]));
+    }
+
+    #[test]
+    fn test_command_line_overrides_file() {
+        let file: FileConfig = toml::from_str(EXAMPLE).unwrap();
+        let (mut cli, matches) = parse_cli(&[
+            "scinit",
+            "--ports",
+            "9090",
+            "--restart",
+            "always",
+            "--restart-max-attempts",
+            "5",
+            "other",
+        ]);
+        file.apply_to(&mut cli, &matches).unwrap();
+
+        assert_eq!(cli.command.as_deref(), Some("other"));
+        assert!(cli.args.is_empty());
+        assert_eq!(cli.ports, vec![9090]);
+        assert_eq!(cli.restart, RestartPolicy::Always);
+        assert_eq!(cli.restart_max_attempts, 5);
+        // Not given on the command line, so still taken from the file
+        assert_eq!(cli.restart_mode, RestartMode::Overlap);
+    }
+
+    #[test]
+    fn test_check_config_subcommand() {
+        let (cli, _) = parse_cli(&["scinit", "check-config", "--config", "x.toml"]);
+        assert_eq!(cli.action, Some(Action::CheckConfig));
+        assert_eq!(cli.config, Some(PathBuf::from("x.toml")));
+
+        // Only the first word can be the subcommand
+        let (cli, _) = parse_cli(&["scinit", "echo", "check-config"]);
+        assert_eq!(cli.action, None);
+        assert_eq!(cli.command.as_deref(), Some("echo"));
+        assert_eq!(cli.args, vec!["check-config"]);
+
+        // A command named like the subcommand follows `--`
+        let (cli, _) = parse_cli(&["scinit", "--", "check-config"]);
+        assert_eq!(cli.action, None);
+        assert_eq!(cli.command.as_deref(), Some("check-config"));
+
+        // The subcommand doesn't mix with the flags for running a command
+        assert!(Cli::command().try_get_matches_from(["scinit", "check-config", "--ports", "80"]).is_err());
+    }
+
+    #[test]
+    fn test_file_environment_and_relative_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("scinit.toml");
+        std::fs::write(
+            &path,
+            r#"
+            command = "app"
+            working_directory = "srv"
+            env_files = [".env", "/etc/app.env"]
+
+            [env]
+            RUST_LOG = "info"
+            PORT = "80"
+
+            [watch]
+            paths = ["src"]
+
+            [[service]]
+            name = "logs"
+            command = "vector"
+            working_directory = "logs"
+            "#,
+        )
+        .unwrap();
+
+        let file = FileConfig::load(&path).unwrap();
+        assert_eq!(file.working_directory, Some(temp_dir.path().join("srv")));
+        assert_eq!(file.env_files, Some(vec![temp_dir.path().join(".env"), PathBuf::from("/etc/app.env")]));
+        assert_eq!(file.watch.paths, Some(vec![temp_dir.path().join("src")]));
+        assert_eq!(file.services[0].working_directory, Some(temp_dir.path().join("logs")));
+
+        let path = path.display().to_string();
+        let (cli, matches) = parse_cli(&["scinit", "--config", &path, "--env", "PORT=8080"]);
+        let config = Config::load(cli, &matches).unwrap();
+        assert_eq!(config.environment.get("RUST_LOG").map(String::as_str), Some("info"));
+        // --env takes precedence over [env]
+        assert_eq!(config.environment.get("PORT").map(String::as_str), Some("8080"));
+    }
+
+    #[test]
+    fn test_invalid_files_are_rejected() {
+        assert!(toml::from_str::<FileConfig>("bogus = 1").is_err());
+        assert!(toml::from_str::<FileConfig>("[restart]\nretries = 1").is_err());
+
+        let (mut cli, matches) = parse_cli(&["scinit"]);
+        let file: FileConfig = toml::from_str("[restart]\npolicy = \"sometimes\"").unwrap();
+        let err = file.apply_to(&mut cli, &matches).unwrap_err();
+        assert!(err.to_string().contains("expected one of never, on-failure, always"));
+
+        let (mut cli, matches) = parse_cli(&["scinit"]);
+        let file: FileConfig = toml::from_str("[ready]\nprobe = \"grpc://x\"").unwrap();
+        assert!(file.apply_to(&mut cli, &matches).is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use super::Result;
+use eyre::eyre;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::str::Chars;
+
+/// How the child's environment is assembled
+///
+/// The child starts from scinit's own environment, filtered by `clear` and
+/// `allow`, then the env files are applied in order. Variables set with
+/// `--env` or in the config file are applied last by the process manager.
+#[derive(Debug, Clone, Default)]
+pub struct EnvConfig {
+    /// Don't pass scinit's environment on, apart from allowed variables
+    pub clear: bool,
+    /// Inherited variables to pass on: exact names or prefixes ending in `*`
+    ///
+    /// A non-empty list implies `clear` for every other variable.
+    pub allow: Vec<String>,
+    /// Dotenv-style files, read on every spawn
+    pub files: Vec<PathBuf>,
+}
+
+impl EnvConfig {
+    /// Checks whether an inherited variable is passed on to the child
+    pub fn inherits(&self, key: &str) -> bool {
+        if !self.clear && self.allow.is_empty() {
+            return true;
+        }
+        self.allow.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == pattern,
+        })
+    }
+
+    /// Builds the environment from the inherited variables and the env files
+    ///
+    /// # Arguments
+    /// * `inherited` - scinit's own environment
+    ///
+    /// # Returns
+    /// * `Result<HashMap<String, String>>` - The environment, or an error naming the bad file
+    pub fn build(&self, inherited: impl IntoIterator<Item = (String, String)>) -> Result<HashMap<String, String>> {
+        let mut vars: HashMap<String, String> = inherited
+            .into_iter()
+            .filter(|(key, _)| self.inherits(key))
+            .collect();
+
+        for path in &self.files {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| eyre!("Failed to read env file {}: {}", path.display(), e))?;
+            parse_env_file(&contents, &mut vars)
+                .map_err(|e| eyre!("Invalid env file {}: {}", path.display(), e))?;
+        }
+        Ok(vars)
+    }
+}
+
+/// Parses a `KEY=VALUE` assignment given on the command line
+///
+/// A bare `KEY` takes the value from scinit's own environment.
+pub fn parse_env_assignment(s: &str) -> Result<(String, String)> {
+    let (key, value) = match s.split_once('=') {
+        Some((key, value)) => (key, value.to_string()),
+        None => (s, std::env::var(s).map_err(|_| eyre!("Variable '{}' is not set in scinit's environment", s))?),
+    };
+    if !is_valid_name(key) {
+        return Err(eyre!("Invalid environment variable name '{}'", key));
+    }
+    Ok((key.to_string(), value))
+}
+
+/// Parses a dotenv-style file into `vars`
+///
+/// Supports `#` comments, an optional `export` prefix, single-quoted
+/// literals, double-quoted values with escapes spanning several lines, and
+/// `${VAR}`, `${VAR:-default}` and `$VAR` interpolation outside single
+/// quotes. Interpolation sees variables assigned earlier in the file as well
+/// as the variables already in `vars`; unknown variables expand to nothing.
+///
+/// # Arguments
+/// * `contents` - The file contents
+/// * `vars` - Variables to interpolate from, updated with the file's assignments
+///
+/// # Returns
+/// * `Result<()>` - Success, or an error with the offending line number
+pub fn parse_env_file(contents: &str, vars: &mut HashMap<String, String>) -> Result<()> {
+    let mut parser = EnvFileParser {
+        chars: contents.chars().peekable(),
+        line: 1,
+    };
+
+    while parser.skip_blank() {
+        let line = parser.line;
+        let (key, value) = parser
+            .assignment(vars)
+            .map_err(|e| eyre!("line {}: {}", line, e))?;
+        vars.insert(key, value);
+    }
+    Ok(())
+}
+
+/// Checks for a valid shell-style variable name
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Character-level parser for env files
+struct EnvFileParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl EnvFileParser<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skips whitespace and comment lines; returns false at the end of input
+    fn skip_blank(&mut self) -> bool {
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.next();
+            } else if c == '#' {
+                self.skip_line();
+            } else {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    /// Parses one `[export] KEY=VALUE` assignment
+    fn assignment(&mut self, vars: &HashMap<String, String>) -> Result<(String, String)> {
+        let mut key = self.name();
+        if key == "export" && self.chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+            while self.chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+                self.next();
+            }
+            key = self.name();
+        }
+        if key.is_empty() {
+            return Err(eyre!("expected a variable name"));
+        }
+        if !is_valid_name(&key) {
+            return Err(eyre!("Invalid environment variable name '{}'", key));
+        }
+        while self.chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+            self.next();
+        }
+        if self.next() != Some('=') {
+            return Err(eyre!("expected '=' after '{}'", key));
+        }
+        while self.chars.peek().is_some_and(|c| *c == ' ' || *c == '\t') {
+            self.next();
+        }
+
+        let value = match self.chars.peek() {
+            Some('\'') => {
+                self.next();
+                let mut value = String::new();
+                loop {
+                    match self.next() {
+                        Some('\'') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(eyre!("unterminated single quote in '{}'", key)),
+                    }
+                }
+                self.end_of_value(&key)?;
+                value
+            }
+            Some('"') => {
+                self.next();
+                let mut value = String::new();
+                loop {
+                    match self.next() {
+                        Some('"') => break,
+                        Some('\\') => match self.next() {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some('r') => value.push('\r'),
+                            Some(c @ ('"' | '\\' | '$')) => value.push(c),
+                            Some(c) => {
+                                value.push('\\');
+                                value.push(c);
+                            }
+                            None => return Err(eyre!("unterminated double quote in '{}'", key)),
+                        },
+                        Some('$') => self.interpolate(&mut value, vars)?,
+                        Some(c) => value.push(c),
+                        None => return Err(eyre!("unterminated double quote in '{}'", key)),
+                    }
+                }
+                self.end_of_value(&key)?;
+                value
+            }
+            _ => {
+                let mut value = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c == '\n' || (c == '#' && value.ends_with([' ', '\t'])) {
+                        break;
+                    }
+                    self.next();
+                    if c == '$' {
+                        self.interpolate(&mut value, vars)?;
+                    } else {
+                        value.push(c);
+                    }
+                }
+                self.skip_line();
+                value.trim_end().to_string()
+            }
+        };
+        Ok((key, value))
+    }
+
+    /// Reads a variable name
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    /// Only whitespace or a comment may follow a quoted value on its line
+    fn end_of_value(&mut self, key: &str) -> Result<()> {
+        while let Some(c) = self.next() {
+            match c {
+                '\n' => return Ok(()),
+                ' ' | '\t' | '\r' => continue,
+                '#' => {
+                    self.skip_line();
+                    return Ok(());
+                }
+                _ => return Err(eyre!("unexpected '{}' after quoted value of '{}'", c, key)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Expands a reference after a `$`
+    fn interpolate(&mut self, value: &mut String, vars: &HashMap<String, String>) -> Result<()> {
+        if self.chars.peek() == Some(&'{') {
+            self.next();
+            let name = self.name();
+            let default = match self.next() {
+                Some('}') => None,
+                Some(':') if self.chars.peek() == Some(&'-') => {
+                    self.next();
+                    let mut default = String::new();
+                    loop {
+                        match self.next() {
+                            Some('}') => break,
+                            Some('\n') | None => return Err(eyre!("unterminated '${{{}'", name)),
+                            Some(c) => default.push(c),
+                        }
+                    }
+                    Some(default)
+                }
+                _ => return Err(eyre!("invalid reference '${{{}'", name)),
+            };
+            if !is_valid_name(&name) {
+                return Err(eyre!("invalid variable name '{}' in reference", name));
+            }
+            match vars.get(&name).filter(|v| !v.is_empty()) {
+                Some(v) => value.push_str(v),
+                None => value.push_str(default.as_deref().unwrap_or("")),
+            }
+        } else {
+            let name = self.name();
+            if name.is_empty() {
+                value.push('$');
+            } else if let Some(v) = vars.get(&name) {
+                value.push_str(v);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str, base: &[(&str, &str)]) -> Result<HashMap<String, String>> {
+        let mut vars = base.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        parse_env_file(contents, &mut vars)?;
+        Ok(vars)
+    }
+
+    #[test]
+    fn test_env_file_parsing() {
+        let vars = parse(
+            r#"
+# database settings
+export DB_HOST=localhost   # trailing comment
+DB_PORT = 5432
+DB_URL="postgres://${DB_HOST}:$DB_PORT/${DB_NAME:-app}"
+LITERAL='no ${EXPANSION} here'
+GREETING="line one\nline \"two\""
+MULTI="a
+b"
+HASH=value#not-a-comment
+EMPTY=
+FROM_PARENT=${HOME}/data
+"#,
+            &[("HOME", "/home/app")],
+        )
+        .unwrap();
+
+        assert_eq!(vars["DB_HOST"], "localhost");
+        assert_eq!(vars["DB_PORT"], "5432");
+        assert_eq!(vars["DB_URL"], "postgres://localhost:5432/app");
+        assert_eq!(vars["LITERAL"], "no ${EXPANSION} here");
+        assert_eq!(vars["GREETING"], "line one\nline \"two\"");
+        assert_eq!(vars["MULTI"], "a\nb");
+        assert_eq!(vars["HASH"], "value#not-a-comment");
+        assert_eq!(vars["EMPTY"], "");
+        assert_eq!(vars["FROM_PARENT"], "/home/app/data");
+    }
+
+    #[test]
+    fn test_env_file_errors_name_the_line() {
+        let err = parse("A=1\nB=\"unterminated\n", &[]).unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+        let err = parse("A=1\n\n=oops\n", &[]).unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+        assert!(parse("A='x' y", &[]).is_err());
+        assert!(parse("A=${B", &[]).is_err());
+        let err = parse("A=1\n1FOO=x\n", &[]).unwrap_err();
+        assert!(err.to_string().contains("Invalid environment variable name '1FOO'"), "{}", err);
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_inheritance_filtering() {
+        let inherited = || {
+            vec![
+                ("PATH".to_string(), "/bin".to_string()),
+                ("APP_MODE".to_string(), "prod".to_string()),
+                ("SECRET".to_string(), "x".to_string()),
+            ]
+        };
+
+        let all = EnvConfig::default().build(inherited()).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let cleared = EnvConfig { clear: true, ..Default::default() }.build(inherited()).unwrap();
+        assert!(cleared.is_empty());
+
+        let allowed = EnvConfig {
+            allow: vec!["APP_*".to_string(), "PATH".to_string()],
+            ..Default::default()
+        }
+        .build(inherited())
+        .unwrap();
+        assert_eq!(allowed.len(), 2);
+        assert!(!allowed.contains_key("SECRET"));
+    }
+
+    #[test]
+    fn test_env_assignment() {
+        assert_eq!(parse_env_assignment("A=b=c").unwrap(), ("A".to_string(), "b=c".to_string()));
+        assert!(parse_env_assignment("1A=b").is_err());
+        assert!(parse_env_assignment("SCINIT_SURELY_UNSET_VARIABLE").is_err());
+    }
+}
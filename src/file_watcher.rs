@@ -1,50 +1,470 @@
 use super::Result;
-use crate::process_manager::ProcessManager;
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use crate::process_manager::{spawn_owned, GroupGuard, ProcessManager, RestartOutcome, RestartReason};
+use eyre::eyre;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use nix::unistd::{access, AccessFlags};
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
 use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::select;
 use tokio::sync::mpsc;
-use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 
 /// Events that can be emitted by the file watcher
 #[derive(Debug, Clone)]
 pub enum FileChangeEvent {
-    /// A file change was detected
-    FileChanged(PathBuf),
+    /// Files changed and the watched paths have since been quiet for the debounce time
+    FilesChanged(Vec<PathBuf>),
     /// An error occurred while watching
     WatchError(String),
 }
 
+/// How the file watcher learns about changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WatchBackend {
+    /// Native notifications, polling directories where they are known or found not to work
+    ///
+    /// Finding out briefly creates a `.scinit-probe-<pid>` file in one writable
+    /// watched directory per file system.
+    Auto,
+    /// The platform's native notifications, such as inotify
+    Native,
+    /// Scanning the watched paths for changes every poll interval
+    Poll,
+}
+
 /// Configuration for file watching behavior
 #[derive(Debug, Clone)]
 pub struct FileWatchConfig {
-    /// Path to watch for changes
-    pub watch_path: PathBuf,
+    /// Files and directories to watch for changes
+    pub watch_paths: Vec<PathBuf>,
     /// Debounce time for file changes (prevents excessive restarts)
     pub debounce_ms: u64,
-    /// Whether to watch recursively
+    /// Whether to watch directories recursively
     pub recursive: bool,
+    /// Globs a changed file in a watched directory must match, relative to that directory; empty matches all
+    pub include: Vec<String>,
+    /// Globs of changed files in watched directories to disregard, relative to that directory
+    pub exclude: Vec<String>,
+    /// Whether to disregard files ignored by `.gitignore` and `.ignore` files
+    pub use_ignore_files: bool,
+    /// Further files whose changes trigger a restart, such as env files
+    pub extra_paths: Vec<PathBuf>,
+    /// The command's executable; a change to it only counts once the new file is complete
+    pub executable: Option<PathBuf>,
+    /// How changes are detected
+    pub backend: WatchBackend,
+    /// Time between scans of polled paths (ms)
+    pub poll_interval_ms: u64,
+    /// Shell command that must succeed before changes trigger a restart, such as `cargo build`
+    pub on_change_run: Option<String>,
+    /// Directory the command runs in
+    pub working_directory: Option<PathBuf>,
 }
 
 impl Default for FileWatchConfig {
     fn default() -> Self {
         Self {
-            watch_path: PathBuf::from("."),
+            watch_paths: vec![PathBuf::from(".")],
             debounce_ms: 500,
             recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            use_ignore_files: true,
+            extra_paths: Vec::new(),
+            executable: None,
+            backend: WatchBackend::Auto,
+            poll_interval_ms: 1000,
+            on_change_run: None,
+            working_directory: None,
+        }
+    }
+}
+
+/// How long a changed executable's size and mtime must stay the same to count as complete
+const STABLE_WINDOW: Duration = Duration::from_millis(100);
+
+/// How long to wait for a changed executable to become complete before giving up on it
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the startup self-test waits for native events about its probe files
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// File systems whose changes made elsewhere raise no native events: NFS, SMB/CIFS, 9p and FUSE (including virtiofs)
+#[cfg(target_os = "linux")]
+const REMOTE_FILE_SYSTEMS: [u32; 6] = [0x6969, 0x517b, 0xff53_4d42, 0xfe53_4d42, 0x0102_1997, 0x6573_5546];
+
+/// Finds the executable a command runs, searching PATH for bare names
+pub fn resolve_executable(command: &str) -> Option<PathBuf> {
+    if command.contains('/') {
+        return Some(PathBuf::from(command));
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|path| path.is_file())
+}
+
+/// Checks that an executable has been completely written and can be run
+///
+/// The size and modification time must not change over `window`, the file
+/// must have an executable bit set, and it must start with ELF magic or a
+/// shebang line.
+///
+/// # Arguments
+/// * `path` - The executable
+/// * `window` - How long the file must stay unchanged
+///
+/// # Returns
+/// * `Result<()>` - Success, or an error saying why the file isn't ready
+pub async fn check_executable(path: &Path, window: Duration) -> Result<()> {
+    use std::io::Read;
+    use std::os::unix::fs::PermissionsExt;
+
+    let snapshot = || -> Result<(u64, Option<std::time::SystemTime>, u32)> {
+        let metadata = std::fs::metadata(path).map_err(|e| eyre!("{}: {}", path.display(), e))?;
+        Ok((metadata.len(), metadata.modified().ok(), metadata.permissions().mode()))
+    };
+    let before = snapshot()?;
+    tokio::time::sleep(window).await;
+    let (len, modified, mode) = snapshot()?;
+    if (len, modified) != (before.0, before.1) {
+        return Err(eyre!("{} is still being written", path.display()));
+    }
+    if mode & 0o111 == 0 {
+        return Err(eyre!("{} is not executable", path.display()));
+    }
+
+    let mut magic = [0u8; 4];
+    let read = std::fs::File::open(path)
+        .and_then(|mut file| file.read(&mut magic))
+        .map_err(|e| eyre!("{}: {}", path.display(), e))?;
+    if !(magic[..read].starts_with(b"\x7fELF") || magic[..read].starts_with(b"#!")) {
+        return Err(eyre!("{} has neither an ELF header nor a shebang line", path.display()));
+    }
+    Ok(())
+}
+
+/// Compiles glob patterns into a set
+///
+/// # Arguments
+/// * `patterns` - Globs such as `**/*.rs` or `target/**`
+///
+/// # Returns
+/// * `Result<GlobSet>` - The compiled set, or an error naming the bad pattern
+pub fn build_globset(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| eyre!("Invalid glob '{}': {}", pattern, e))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Checks whether a directory is on a file system where changes made elsewhere raise no native events
+fn is_remote_file_system(dir: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        nix::sys::statfs::statfs(dir)
+            .is_ok_and(|stat| REMOTE_FILE_SYSTEMS.contains(&(stat.filesystem_type().0 as u32)))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = dir;
+        false
+    }
+}
+
+/// Finds the directories native notifications don't work for
+///
+/// Directories on network and FUSE file systems are polled outright. On the
+/// others, a probe file is created and removed again in one writable
+/// directory per file system; if it raises no native event within
+/// `PROBE_TIMEOUT`, every directory on that file system is polled. File
+/// systems without a writable directory to probe are polled as well.
+///
+/// # Arguments
+/// * `dirs` - The directories about to be watched
+///
+/// # Returns
+/// * `Vec<bool>` - For each directory, whether it needs polling
+async fn needs_polling(dirs: &[&Path]) -> Vec<bool> {
+    let mut polled: Vec<bool> = dirs.iter().map(|dir| is_remote_file_system(dir)).collect();
+    for (dir, _) in dirs.iter().zip(&polled).filter(|(_, polled)| **polled) {
+        info!("Polling {:?}: its file system doesn't report changes made elsewhere", dir);
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let watcher = RecommendedWatcher::new(
+        move |res: std::result::Result<notify::Event, notify::Error>| {
+            if let Ok(event) = res {
+                let _ = tx.send(event.paths);
+            }
+        },
+        notify::Config::default(),
+    );
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Native file watching is unavailable, polling instead: {}", e);
+            return vec![true; dirs.len()];
+        }
+    };
+
+    // One probe per file system, in the first watched directory writable there
+    let devices: Vec<Option<u64>> = dirs.iter().map(|dir| std::fs::metadata(dir).ok().map(|m| m.dev())).collect();
+    let probe_name = format!(".scinit-probe-{}", std::process::id());
+    let mut probes: Vec<(u64, PathBuf)> = Vec::new();
+    for (index, dir) in dirs.iter().enumerate() {
+        let Some(device) = devices[index] else {
+            continue;
+        };
+        if polled[index] || probes.iter().any(|(probed, _)| *probed == device) {
+            continue;
+        }
+        if access(*dir, AccessFlags::W_OK).is_err() {
+            continue;
+        }
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            debug!("Cannot watch {:?} natively: {}", dir, e);
+            continue;
+        }
+        let probe = dir.join(&probe_name);
+        match std::fs::write(&probe, b"") {
+            Ok(()) => probes.push((device, probe)),
+            Err(e) => debug!("Cannot probe native events in {:?}: {}", dir, e),
+        }
+    }
+
+    let deadline = tokio::time::Instant::now() + PROBE_TIMEOUT;
+    let mut unseen: Vec<&(u64, PathBuf)> = probes.iter().collect();
+    while !unseen.is_empty() {
+        match tokio::time::timeout_at(deadline, rx.recv()).await {
+            Ok(Some(paths)) => unseen.retain(|(_, probe)| !paths.contains(probe)),
+            _ => break,
+        }
+    }
+
+    for (index, dir) in dirs.iter().enumerate() {
+        if polled[index] {
+            continue;
+        }
+        let probe = devices[index].and_then(|device| probes.iter().find(|(probed, _)| *probed == device));
+        match probe {
+            Some(probe) if unseen.contains(&probe) => {
+                warn!("No native events arrived for {:?}, polling instead", dir);
+                polled[index] = true;
+            }
+            Some(_) => {}
+            None => {
+                debug!("No writable directory to probe native events for {:?}, polling instead", dir);
+                polled[index] = true;
+            }
         }
     }
+
+    drop(watcher);
+    for (_, probe) in &probes {
+        if let Err(e) = std::fs::remove_file(probe) {
+            warn!("Failed to remove probe file {:?}: {}", probe, e);
+        }
+    }
+    polled
+}
+
+/// A running `on_change_run` command; dropping it cancels the command
+type Build = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// Runs the `on_change_run` command and streams its output to the log
+///
+/// The command runs through `sh -c` in its own process group, so cancelling
+/// the build also stops the compilers it started.
+///
+/// # Arguments
+/// * `command` - Shell command to run
+/// * `working_directory` - Directory to run it in, or None for scinit's own
+///
+/// # Returns
+/// * `Result<()>` - Success, or an error if the command failed
+async fn run_build(command: String, working_directory: Option<PathBuf>) -> Result<()> {
+    let mut build = Command::new("sh");
+    build
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .kill_on_drop(true);
+    if let Some(dir) = working_directory {
+        build.current_dir(dir);
+    }
+    let mut child = spawn_owned(&mut build).map_err(|e| eyre!("Failed to run '{}': {}", command, e))?;
+    let mut group = GroupGuard::new(&child);
+
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    let (status, _, _) = tokio::join!(child.wait(), log_output(stdout), log_output(stderr));
+    group.disarm();
+
+    let status = status.map_err(|e| eyre!("Failed to wait for '{}': {}", command, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(eyre!("'{}' exited with {}", command, status))
+    }
+}
+
+/// Logs each line a build writes
+async fn log_output(stream: Option<impl AsyncRead + Unpin>) {
+    let Some(stream) = stream else {
+        return;
+    };
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        info!("build: {}", line);
+    }
+}
+
+/// Waits for the running build to finish, or forever if there is none
+async fn build_finished(build: &mut Option<Build>) -> Result<()> {
+    match build {
+        Some(build) => build.await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Decides which changed files are worth a restart
+///
+/// Files watched by name are always relevant. Files inside watched
+/// directories are matched against the include and exclude globs relative
+/// to their directory, then against the ignore files that apply to them:
+/// those from the directory up to the enclosing git repository's root and,
+/// when watching recursively, those found below it at startup.
+#[derive(Debug)]
+struct PathFilter {
+    /// Files watched by name
+    explicit: Vec<PathBuf>,
+    /// Watched directories
+    roots: Vec<PathBuf>,
+    include: GlobSet,
+    exclude: GlobSet,
+    /// Ignore matchers, deepest directory first
+    ignores: Vec<Gitignore>,
+}
+
+impl PathFilter {
+    /// Builds the filter for canonical watch paths
+    fn new(config: &FileWatchConfig, watch_paths: &[PathBuf], extra_paths: &[PathBuf]) -> Result<Self> {
+        let (roots, mut explicit): (Vec<PathBuf>, Vec<PathBuf>) = watch_paths.iter().cloned().partition(|path| path.is_dir());
+        explicit.extend(extra_paths.iter().cloned());
+
+        let mut ignores = Vec::new();
+        if config.use_ignore_files {
+            let mut dirs: Vec<PathBuf> = Vec::new();
+            for root in &roots {
+                // The repository's ignore files apply to a watched subdirectory too
+                let repository = root.ancestors().find(|dir| dir.join(".git").exists()).unwrap_or(root);
+                dirs.extend(root.ancestors().take_while(|dir| dir.starts_with(repository)).map(Path::to_path_buf));
+                if config.recursive {
+                    let walker = ignore::WalkBuilder::new(root)
+                        .hidden(false)
+                        .filter_entry(|entry| entry.file_name() != ".git")
+                        .build();
+                    dirs.extend(
+                        walker
+                            .flatten()
+                            .filter(|entry| entry.file_type().is_some_and(|t| t.is_dir()))
+                            .map(|entry| entry.into_path()),
+                    );
+                }
+            }
+            dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+            dirs.dedup();
+
+            for dir in dirs {
+                let mut builder = GitignoreBuilder::new(&dir);
+                let mut found = false;
+                // .ignore is added last so it takes precedence, as in ripgrep
+                for name in [".gitignore", ".ignore"] {
+                    let file = dir.join(name);
+                    if file.is_file() {
+                        if let Some(e) = builder.add(&file) {
+                            warn!("Failed to read {}: {}", file.display(), e);
+                        }
+                        found = true;
+                    }
+                }
+                if found {
+                    ignores.push(builder.build()?);
+                }
+            }
+        }
+
+        Ok(PathFilter {
+            explicit,
+            roots,
+            include: build_globset(&config.include)?,
+            exclude: build_globset(&config.exclude)?,
+            ignores,
+        })
+    }
+
+    /// Checks whether a change to `path` should trigger a restart
+    fn allows(&self, path: &Path) -> bool {
+        if self.explicit.iter().any(|explicit| explicit == path) {
+            return true;
+        }
+        let Some(relative) = self
+            .roots
+            .iter()
+            .filter_map(|root| path.strip_prefix(root).ok())
+            .min_by_key(|relative| relative.components().count())
+        else {
+            return false;
+        };
+
+        if relative.components().any(|component| component.as_os_str() == ".git") {
+            return false;
+        }
+        if self.exclude.is_match(relative) {
+            return false;
+        }
+        if !self.include.is_empty() && !self.include.is_match(relative) {
+            return false;
+        }
+
+        // The deepest ignore file with an opinion decides
+        for ignore in &self.ignores {
+            if !path.starts_with(ignore.path()) {
+                continue;
+            }
+            let matched = ignore.matched_path_or_any_parents(path, false);
+            if matched.is_ignore() {
+                return false;
+            }
+            if matched.is_whitelist() {
+                return true;
+            }
+        }
+        true
+    }
 }
 
 /// Async file watcher that monitors files for changes and emits events
 /// 
 /// This watcher uses the `notify` crate for cross-platform file system monitoring
 /// and includes debouncing to prevent excessive restarts when files are being
-/// written or compiled.
+/// written or compiled. Directories native notifications don't work for are
+/// polled instead.
 pub struct FileWatcher {
-    /// The underlying notify watcher
-    watcher: Option<RecommendedWatcher>,
+    /// The underlying notify watchers, native and polling
+    watchers: Vec<Box<dyn Watcher + Send>>,
     /// Configuration for the watcher
     config: FileWatchConfig,
     /// Channel sender for file change events
@@ -65,7 +485,7 @@ impl FileWatcher {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         
         Ok(FileWatcher {
-            watcher: None,
+            watchers: Vec::new(),
             config,
             event_tx,
             event_rx,
@@ -81,65 +501,179 @@ impl FileWatcher {
     /// * `Result<()>` - Success or error
     pub async fn start_watching(&mut self) -> Result<()> {
         let (tx, mut rx) = mpsc::channel(100);
-        
-        // Create the notify watcher
-        let mut watcher = RecommendedWatcher::new(
+        let handler = |tx: mpsc::Sender<_>| {
             move |res: std::result::Result<notify::Event, notify::Error>| {
                 if let Err(e) = tx.blocking_send(res) {
                     error!("Failed to send file change event: {}", e);
                 }
-            },
-            notify::Config::default(),
-        )?;
+            }
+        };
 
-        // Start watching the configured path
-        let watch_path = self.config.watch_path.clone();
+        // Watch canonical paths, so event paths can be matched against them
+        let canonical = |path: &PathBuf| {
+            path.canonicalize()
+                .map_err(|e| eyre!("Cannot watch {}: {}", path.display(), e))
+        };
+        let watch_paths = self.config.watch_paths.iter().map(canonical).collect::<Result<Vec<_>>>()?;
+        let extra_paths = self.config.extra_paths.iter().map(canonical).collect::<Result<Vec<_>>>()?;
         let recursive_mode = if self.config.recursive {
             RecursiveMode::Recursive
         } else {
             RecursiveMode::NonRecursive
         };
 
-        watcher.watch(&watch_path, recursive_mode)?;
-        info!("Started watching path: {:?}", watch_path);
+        let (roots, files): (Vec<&PathBuf>, Vec<&PathBuf>) =
+            watch_paths.iter().chain(&extra_paths).partition(|path| path.is_dir());
+        let mut watches: Vec<(&Path, RecursiveMode)> = roots.iter().map(|root| (root.as_path(), recursive_mode)).collect();
+        // Files are watched through their directory: a watch on the file itself
+        // would stay with the old inode when a new version is renamed over it
+        for path in &files {
+            let parent = path.parent().unwrap_or(path);
+            let covered = roots
+                .iter()
+                .any(|root| parent == root.as_path() || (self.config.recursive && parent.starts_with(root)));
+            if !covered && !watches.iter().any(|(dir, _)| *dir == parent) {
+                watches.push((parent, RecursiveMode::NonRecursive));
+            }
+        }
 
-        // Store the watcher
-        self.watcher = Some(watcher);
+        let dirs: Vec<&Path> = watches.iter().map(|(dir, _)| *dir).collect();
+        let polled = match self.config.backend {
+            WatchBackend::Auto => needs_polling(&dirs).await,
+            WatchBackend::Native => vec![false; dirs.len()],
+            WatchBackend::Poll => vec![true; dirs.len()],
+        };
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        let mut native: Option<RecommendedWatcher> = None;
+        let mut poll: Option<PollWatcher> = None;
+        for ((dir, mode), polled) in watches.into_iter().zip(polled) {
+            if !polled {
+                let watcher = match &mut native {
+                    Some(watcher) => watcher,
+                    None => native.insert(RecommendedWatcher::new(handler(tx.clone()), notify::Config::default())?),
+                };
+                match watcher.watch(dir, mode) {
+                    Ok(()) => continue,
+                    // Such as running out of inotify watches on a large tree
+                    Err(e) if self.config.backend == WatchBackend::Auto => {
+                        warn!("Cannot watch {:?} natively, polling instead: {}", dir, e)
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            let watcher = match &mut poll {
+                Some(watcher) => watcher,
+                None => poll.insert(PollWatcher::new(
+                    handler(tx.clone()),
+                    notify::Config::default().with_poll_interval(poll_interval),
+                )?),
+            };
+            watcher.watch(dir, mode)?;
+            debug!("Polling {:?} every {:?}", dir, poll_interval);
+        }
+        for path in roots.iter().chain(&files) {
+            info!("Started watching path: {:?}", path);
+        }
+        let filter = PathFilter::new(&self.config, &watch_paths, &extra_paths)?;
+        debug!("File change filter: {:?}", filter);
+        let executable = self.config.executable.as_ref().and_then(|path| path.canonicalize().ok());
+        let on_change_run = self.config.on_change_run.clone();
+        let working_directory = self.config.working_directory.clone();
+
+        // Store the watchers
+        self.watchers.extend(native.map(|watcher| Box::new(watcher) as Box<dyn Watcher + Send>));
+        self.watchers.extend(poll.map(|watcher| Box::new(watcher) as Box<dyn Watcher + Send>));
 
         // Spawn the event processing task
         let event_tx = self.event_tx.clone();
-        let debounce_ms = self.config.debounce_ms;
+        let debounce = Duration::from_millis(self.config.debounce_ms);
         
         tokio::spawn(async move {
-            let mut last_change = None;
-            
-            while let Some(res) = rx.recv().await {
-                match res {
-                    Ok(event) => {
-                        debug!("File system event: {:?}", event);
-                        
-                        // Check if this is a file modification event
-                        if Self::is_relevant_change(&event) {
-                            let now = std::time::Instant::now();
-                            
-                            // Debounce the change
-                            if let Some(last) = last_change {
-                                if now.duration_since(last).as_millis() < debounce_ms as u128 {
-                                    debug!("Debouncing file change");
-                                    continue;
+            // Trailing-edge debounce: collect changed paths until nothing
+            // relevant has happened for the debounce time, then emit them all
+            let mut changed = BTreeSet::new();
+            let mut quiet_at = tokio::time::Instant::now();
+            let mut settling_since = None;
+            let mut build: Option<Build> = None;
+
+            loop {
+                let res = select! {
+                    res = rx.recv() => match res {
+                        Some(res) => res,
+                        None => break,
+                    },
+                    result = build_finished(&mut build) => {
+                        build = None;
+                        let paths: Vec<PathBuf> = std::mem::take(&mut changed).into_iter().collect();
+                        match result {
+                            Ok(()) => {
+                                info!("Build succeeded");
+                                if let Err(e) = event_tx.send(FileChangeEvent::FilesChanged(paths)) {
+                                    error!("Failed to send file change event: {}", e);
+                                    break;
                                 }
                             }
-                            
-                            last_change = Some(now);
-                            
-                            // Emit the change event with canonicalized path for consistency
-                            let path = event.paths.first().unwrap_or(&watch_path);
-                            let canonical_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-                            if let Err(e) = event_tx.send(FileChangeEvent::FileChanged(canonical_path)) {
-                                error!("Failed to send file change event: {}", e);
-                                break;
+                            Err(e) => warn!("Build failed, keeping the running process: {}", e),
+                        }
+                        continue;
+                    }
+                    _ = tokio::time::sleep_until(quiet_at), if !changed.is_empty() && build.is_none() => {
+                        // A new build of the command must be complete before restarting into it
+                        if let Some(executable) = executable.as_ref().filter(|path| changed.contains(*path)) {
+                            if let Err(e) = check_executable(executable, STABLE_WINDOW).await {
+                                let since = *settling_since.get_or_insert_with(tokio::time::Instant::now);
+                                if since.elapsed() < SETTLE_TIMEOUT {
+                                    debug!("Waiting for {:?} to settle: {}", executable, e);
+                                    quiet_at = tokio::time::Instant::now() + debounce;
+                                } else {
+                                    warn!("Not restarting: {}", e);
+                                    changed.clear();
+                                    settling_since = None;
+                                }
+                                continue;
                             }
                         }
+                        settling_since = None;
+
+                        if let Some(command) = &on_change_run {
+                            info!("Running '{}'", command);
+                            build = Some(Box::pin(run_build(command.clone(), working_directory.clone())));
+                            continue;
+                        }
+
+                        let paths: Vec<PathBuf> = std::mem::take(&mut changed).into_iter().collect();
+                        if let Err(e) = event_tx.send(FileChangeEvent::FilesChanged(paths)) {
+                            error!("Failed to send file change event: {}", e);
+                            break;
+                        }
+                        continue;
+                    }
+                };
+
+                match res {
+                    Ok(event) => {
+                        debug!("File system event: {:?}", event);
+
+                        // Check if this is a modification of files that pass the filters
+                        // Canonicalized paths for consistency
+                        let mut paths: Vec<PathBuf> = Self::relevant_paths(&event, &filter)
+                            .into_iter()
+                            .map(|path| path.canonicalize().unwrap_or_else(|_| path.clone()))
+                            .collect();
+                        // With a build command the executable is the build's output, not a source
+                        if on_change_run.is_some() {
+                            paths.retain(|path| Some(path) != executable.as_ref());
+                        }
+                        if paths.is_empty() {
+                            continue;
+                        }
+                        if build.take().is_some() {
+                            info!("Files changed during the build, starting it again");
+                        } else if !changed.is_empty() {
+                            debug!("Debouncing file change");
+                        }
+                        changed.extend(paths);
+                        quiet_at = tokio::time::Instant::now() + debounce;
                     }
                     Err(e) => {
                         error!("File watching error: {}", e);
@@ -155,45 +689,37 @@ impl FileWatcher {
         Ok(())
     }
 
-
-    /// Waits for the next file change event
-    /// 
-    /// This method waits for the next file change event with an optional timeout.
+    /// Picks the paths of a file system event that are relevant for triggering a restart
     /// 
     /// # Arguments
-    /// * `timeout_duration` - Maximum time to wait for an event
+    /// * `event` - The file system event to check
+    /// * `filter` - Include, exclude and ignore-file rules for the changed paths
     /// 
     /// # Returns
-    /// * `Result<Option<FileChangeEvent>>` - The event or None if timeout
-    pub async fn wait_for_event(&mut self, timeout_duration: Duration) -> Result<Option<FileChangeEvent>> {
-        match timeout(timeout_duration, self.event_rx.recv()).await {
-            Ok(Some(event)) => Ok(Some(event)),
-            Ok(None) => Ok(None), // Channel closed
-            Err(_) => Ok(None),   // Timeout
+    /// * `Vec<&PathBuf>` - The changed files that should trigger a restart
+    fn relevant_paths<'a>(event: &'a notify::Event, filter: &PathFilter) -> Vec<&'a PathBuf> {
+        // Modifications, including renames, and creations count
+        if !event.kind.is_modify() && !event.kind.is_create() {
+            return Vec::new();
         }
+
+        // Keep changed paths that are files (not directories) and pass the filters
+        event
+            .paths
+            .iter()
+            .filter(|path| {
+                let is_file = std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file());
+                is_file && filter.allows(path)
+            })
+            .collect()
     }
 
-    /// Checks if a file system event is relevant for triggering a restart
-    /// 
-    /// # Arguments
-    /// * `event` - The file system event to check
-    /// 
+    /// Waits for the next file change event
+    ///
     /// # Returns
-    /// * `bool` - True if the event should trigger a restart
-    fn is_relevant_change(event: &notify::Event) -> bool {
-        // Check if this is a file modification event
-        if !event.kind.is_modify() {
-            return false;
-        }
-
-        // Check if any of the changed paths are files (not directories)
-        event.paths.iter().any(|path| {
-            if let Ok(metadata) = std::fs::metadata(path) {
-                metadata.is_file()
-            } else {
-                false
-            }
-        })
+    /// * `Option<FileChangeEvent>` - The event, or None once the watcher has stopped
+    pub async fn next_event(&mut self) -> Option<FileChangeEvent> {
+        self.event_rx.recv().await
     }
 
 }
@@ -201,33 +727,36 @@ impl FileWatcher {
 impl Drop for FileWatcher {
     fn drop(&mut self) {
         // Ensure we stop watching when dropped
-        if self.watcher.is_some() {
-            // Don't try to use block_on in a Drop implementation
-            // Just drop the watcher directly
-            self.watcher.take();
-        }
+        // Don't try to use block_on in a Drop implementation
+        // Just drop the watchers directly
+        self.watchers.clear();
     }
 }
 
-/// Handles file change events and triggers process restarts
-pub async fn handle_file_events(file_watcher: &mut Option<FileWatcher>, process_manager: &mut ProcessManager) -> Result<bool> {
-    if let Some(ref mut file_watcher) = file_watcher {
-        if let Some(event) = file_watcher.wait_for_event(Duration::from_millis(100)).await? {
-            match event {
-                FileChangeEvent::FileChanged(path) => {
-                    info!("File changed: {:?}, triggering restart", path);
-                    let restart_result = process_manager
-                        .restart_process_with_reason("file_change")
-                        .await?;
-                    if !restart_result {
-                        info!("Process restart limit exceeded, exiting");
-                        return Ok(true); // Signal to exit
-                    }
-                }
-                FileChangeEvent::WatchError(error) => {
-                    warn!("File watching error: {}", error);
-                }
+/// Handles a file change event and triggers a process restart
+///
+/// # Returns
+/// * `Result<bool>` - True if scinit should exit; the process manager is
+///   `Failed` if that is because the restart budget ran out
+pub async fn handle_file_event(event: FileChangeEvent, process_manager: &mut ProcessManager) -> Result<bool> {
+    match event {
+        FileChangeEvent::FilesChanged(paths) => {
+            match paths.as_slice() {
+                [path] => info!("File changed: {:?}, triggering restart", path),
+                _ => info!("{} files changed: {:?}, triggering restart", paths.len(), paths),
             }
+            let restart_result = process_manager
+                .restart_process_with_reason(RestartReason::FileChange)
+                .await?;
+            match restart_result {
+                RestartOutcome::NotAllowed => info!("Process restart not allowed, exiting"),
+                RestartOutcome::Exhausted => info!("Process restart limit exceeded, exiting"),
+                RestartOutcome::Restarted | RestartOutcome::Kept => return Ok(false),
+            }
+            return Ok(true); // Signal to exit
+        }
+        FileChangeEvent::WatchError(error) => {
+            warn!("File watching error: {}", error);
         }
     }
     Ok(false) // Continue normal operation
@@ -238,6 +767,11 @@ mod tests {
     use super::*;
     use std::fs;
     use tempfile::tempdir;
+    use tokio::time::timeout;
+
+    async fn event_within(watcher: &mut FileWatcher, ms: u64) -> Option<FileChangeEvent> {
+        timeout(Duration::from_millis(ms), watcher.next_event()).await.ok().flatten()
+    }
 
     #[tokio::test]
     async fn test_file_watcher_creation() {
@@ -250,9 +784,9 @@ mod tests {
     async fn test_file_watcher_start() {
         let temp_dir = tempdir().unwrap();
         let config = FileWatchConfig {
-            watch_path: temp_dir.path().to_path_buf(),
+            watch_paths: vec![temp_dir.path().to_path_buf()],
             debounce_ms: 100,
-            recursive: false,
+            ..Default::default()
         };
 
         let mut watcher = FileWatcher::new(config).unwrap();
@@ -267,9 +801,9 @@ mod tests {
     async fn test_file_change_detection() {
         let temp_dir = tempdir().unwrap();
         let config = FileWatchConfig {
-            watch_path: temp_dir.path().to_path_buf(),
+            watch_paths: vec![temp_dir.path().to_path_buf()],
             debounce_ms: 100,
-            recursive: false,
+            ..Default::default()
         };
 
         let mut watcher = FileWatcher::new(config).unwrap();
@@ -280,15 +814,15 @@ mod tests {
         fs::write(&test_file, "test content").unwrap();
 
         // Wait for the file change event
-        let event = watcher.wait_for_event(Duration::from_millis(1000)).await.unwrap();
+        let event = event_within(&mut watcher, 1000).await;
         assert!(event.is_some());
 
-        if let Some(FileChangeEvent::FileChanged(path)) = event {
+        if let Some(FileChangeEvent::FilesChanged(paths)) = event {
             // The FileWatcher now emits canonical paths, so compare with canonical test file path
             let canonical_test_file = test_file.canonicalize().unwrap_or(test_file);
-            assert_eq!(path, canonical_test_file);
+            assert_eq!(paths, vec![canonical_test_file]);
         } else {
-            panic!("Expected FileChanged event");
+            panic!("Expected FilesChanged event");
         }
 
         // Watcher will be dropped automatically
@@ -298,54 +832,64 @@ mod tests {
     async fn test_debouncing() {
         let temp_dir = tempdir().unwrap();
         let config = FileWatchConfig {
-            watch_path: temp_dir.path().to_path_buf(),
+            watch_paths: vec![temp_dir.path().to_path_buf()],
             debounce_ms: 500,
-            recursive: false,
+            ..Default::default()
         };
 
         let mut watcher = FileWatcher::new(config).unwrap();
         watcher.start_watching().await.unwrap();
 
-        let test_file = temp_dir.path().join("test.txt");
-        
-        // Write to file multiple times quickly
-        for i in 0..5 {
-            fs::write(&test_file, format!("content {}", i)).unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+
+        // Write to several files quickly, for longer than the debounce time
+        let start = tokio::time::Instant::now();
+        for i in 0..12 {
+            fs::write(root.join(format!("test{}.txt", i % 3)), format!("content {}", i)).unwrap();
             tokio::time::sleep(Duration::from_millis(50)).await;
         }
 
-        // Should only get one event due to debouncing
-        let event = watcher.wait_for_event(Duration::from_millis(1000)).await.unwrap();
-        assert!(event.is_some());
+        // Nothing is emitted while writes keep coming...
+        assert!(start.elapsed() > Duration::from_millis(500));
+        assert!(event_within(&mut watcher, 200).await.is_none());
 
-        // Should not get more events immediately
-        let event2 = watcher.wait_for_event(Duration::from_millis(200)).await.unwrap();
-        assert!(event2.is_none());
+        // ...then a single event carries every changed file
+        match event_within(&mut watcher, 1000).await {
+            Some(FileChangeEvent::FilesChanged(paths)) => {
+                assert_eq!(paths, vec![root.join("test0.txt"), root.join("test1.txt"), root.join("test2.txt")]);
+            }
+            other => panic!("Expected FilesChanged event, got {:?}", other),
+        }
+
+        // Should not get more events afterwards
+        assert!(event_within(&mut watcher, 700).await.is_none());
 
         // Watcher will be dropped automatically
     }
 
     #[test]
-    fn test_is_relevant_change() {
+    fn test_relevant_paths() {
         use notify::EventKind;
         use tempfile::tempdir;
 
         // Create a temporary directory and file for testing
         let temp_dir = tempdir().unwrap();
-        let test_file = temp_dir.path().join("test.txt");
+        let root = temp_dir.path().canonicalize().unwrap();
+        let test_file = root.join("test.txt");
         std::fs::write(&test_file, "test content").unwrap();
+        let filter = PathFilter::new(&FileWatchConfig::default(), std::slice::from_ref(&root), &[]).unwrap();
 
         // Test file modification event
         let event = notify::Event {
             kind: EventKind::Modify(notify::event::ModifyKind::Data(notify::event::DataChange::Content)),
-            paths: vec![test_file],
+            paths: vec![test_file.clone()],
             attrs: notify::event::EventAttributes::default(),
         };
 
-        assert!(FileWatcher::is_relevant_change(&event));
+        assert_eq!(FileWatcher::relevant_paths(&event, &filter), vec![&test_file]);
 
         // Test directory modification event (should be ignored)
-        let test_dir = temp_dir.path().join("test_dir");
+        let test_dir = root.join("test_dir");
         std::fs::create_dir(&test_dir).unwrap();
         
         let event = notify::Event {
@@ -355,6 +899,201 @@ mod tests {
         };
 
         // This should be false because it's a directory
-        assert!(!FileWatcher::is_relevant_change(&event));
+        assert!(FileWatcher::relevant_paths(&event, &filter).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let window = Duration::from_millis(20);
+        let script = temp_dir.path().join("app");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        assert!(check_executable(&script, window).await.unwrap_err().to_string().contains("not executable"));
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(check_executable(&script, window).await.is_ok());
+        assert!(check_executable(Path::new("/bin/sh"), window).await.is_ok());
+
+        // A truncated build has neither header
+        fs::write(&script, "").unwrap();
+        assert!(check_executable(&script, window).await.is_err());
+
+        // A file still being written isn't stable
+        let writer = {
+            let script = script.clone();
+            tokio::spawn(async move {
+                for i in 0..10 {
+                    fs::write(&script, format!("#!/bin/sh\n# {}\n", "x".repeat(i))).unwrap();
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+        };
+        assert!(check_executable(&script, window).await.unwrap_err().to_string().contains("still being written"));
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rename_over_watched_file() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let binary = root.join("app");
+        fs::write(&binary, "old").unwrap();
+        let config = FileWatchConfig {
+            watch_paths: vec![binary.clone()],
+            debounce_ms: 50,
+            ..Default::default()
+        };
+        let mut watcher = FileWatcher::new(config).unwrap();
+        watcher.start_watching().await.unwrap();
+
+        // Unrelated files in the same directory don't count
+        fs::write(root.join("other"), "x").unwrap();
+        assert!(event_within(&mut watcher, 300).await.is_none());
+
+        // Twice, since a watch on the file itself would lose track after the first rename
+        for version in ["new", "newer"] {
+            fs::write(root.join("app.tmp"), version).unwrap();
+            fs::rename(root.join("app.tmp"), &binary).unwrap();
+            match event_within(&mut watcher, 1000).await {
+                Some(FileChangeEvent::FilesChanged(paths)) => assert_eq!(paths, vec![binary.clone()]),
+                other => panic!("Expected FilesChanged event, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_backend() {
+        let temp_dir = tempdir().unwrap();
+        let config = FileWatchConfig {
+            watch_paths: vec![temp_dir.path().to_path_buf()],
+            debounce_ms: 50,
+            backend: WatchBackend::Poll,
+            poll_interval_ms: 50,
+            ..Default::default()
+        };
+
+        let mut watcher = FileWatcher::new(config).unwrap();
+        watcher.start_watching().await.unwrap();
+
+        let test_file = temp_dir.path().join("app.conf");
+        fs::write(&test_file, "v2").unwrap();
+        match event_within(&mut watcher, 2000).await {
+            Some(FileChangeEvent::FilesChanged(paths)) => assert_eq!(paths, vec![test_file.canonicalize().unwrap()]),
+            other => panic!("Expected FilesChanged event from polling, got {:?}", other),
+        }
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_native_self_test() {
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        let sibling = dir.join("sibling");
+        fs::create_dir(&sibling).unwrap();
+        let expected = is_remote_file_system(&dir);
+
+        // Both directories share a file system, so one probe decides for both
+        assert_eq!(needs_polling(&[&dir, &sibling]).await, vec![expected, expected]);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1, "probe files are removed");
+        assert_eq!(fs::read_dir(&sibling).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_on_change_run() {
+        let temp_dir = tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        let config = FileWatchConfig {
+            watch_paths: vec![src.clone()],
+            debounce_ms: 50,
+            on_change_run: Some("echo compiling; test ! -e broken".to_string()),
+            working_directory: Some(temp_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let mut watcher = FileWatcher::new(config).unwrap();
+        watcher.start_watching().await.unwrap();
+
+        // A failed build keeps the running process
+        fs::write(temp_dir.path().join("broken"), "").unwrap();
+        fs::write(src.join("main.rs"), "fn main() {").unwrap();
+        assert!(event_within(&mut watcher, 1000).await.is_none());
+
+        fs::remove_file(temp_dir.path().join("broken")).unwrap();
+        fs::write(src.join("main.rs"), "fn main() {}").unwrap();
+        match event_within(&mut watcher, 2000).await {
+            Some(FileChangeEvent::FilesChanged(paths)) => assert_eq!(paths, vec![src.join("main.rs").canonicalize().unwrap()]),
+            other => panic!("Expected FilesChanged event after the build, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_change_during_build_restarts_it() {
+        let temp_dir = tempdir().unwrap();
+        let src = temp_dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        let config = FileWatchConfig {
+            watch_paths: vec![src.clone()],
+            debounce_ms: 50,
+            on_change_run: Some("echo start >> builds; sleep 1; echo done >> builds".to_string()),
+            working_directory: Some(temp_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let mut watcher = FileWatcher::new(config).unwrap();
+        watcher.start_watching().await.unwrap();
+
+        fs::write(src.join("a.rs"), "").unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        fs::write(src.join("b.rs"), "").unwrap();
+
+        match event_within(&mut watcher, 3000).await {
+            Some(FileChangeEvent::FilesChanged(paths)) => assert_eq!(paths.len(), 2),
+            other => panic!("Expected FilesChanged event after the build, got {:?}", other),
+        }
+        // The cancelled build's process group was killed before it finished
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        let builds = fs::read_to_string(temp_dir.path().join("builds")).unwrap();
+        assert_eq!(builds.lines().collect::<Vec<_>>(), vec!["start", "start", "done"]);
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let temp_dir = tempdir().unwrap();
+        let repo = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src/generated")).unwrap();
+        fs::create_dir_all(repo.join("target/debug")).unwrap();
+        fs::write(repo.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(repo.join("src/.gitignore"), "generated/\n!keep.log\n").unwrap();
+        let env_file = repo.join("target/app.env");
+
+        let config = FileWatchConfig {
+            recursive: true,
+            include: vec!["**/*.rs".to_string(), "**/*.log".to_string(), "**/*.env".to_string()],
+            exclude: vec!["target/**".to_string()],
+            ..Default::default()
+        };
+        let filter = PathFilter::new(&config, std::slice::from_ref(&repo), std::slice::from_ref(&env_file)).unwrap();
+
+        assert!(filter.allows(&repo.join("src/main.rs")));
+        assert!(filter.allows(&repo.join("build.rs")));
+        assert!(!filter.allows(&repo.join("README.md")), "not included");
+        assert!(!filter.allows(&repo.join("target/debug/build.rs")), "excluded");
+        assert!(!filter.allows(&repo.join("app.log")), "ignored by the root .gitignore");
+        assert!(filter.allows(&repo.join("src/keep.log")), "re-included by the nested .gitignore");
+        assert!(!filter.allows(&repo.join("src/generated/schema.rs")), "ignored by the nested .gitignore");
+        assert!(!filter.allows(&repo.join(".git/hooks/x.rs")));
+        assert!(filter.allows(&env_file), "files watched by name bypass the filters");
+
+        // A subdirectory still honours the repository's ignore files
+        let filter = PathFilter::new(&FileWatchConfig::default(), &[repo.join("src")], &[]).unwrap();
+        assert!(!filter.allows(&repo.join("src/debug.log")));
+        assert!(filter.allows(&repo.join("src/lib.rs")));
+
+        let unfiltered = FileWatchConfig { use_ignore_files: false, ..Default::default() };
+        let filter = PathFilter::new(&unfiltered, std::slice::from_ref(&repo), &[]).unwrap();
+        assert!(filter.allows(&repo.join("app.log")));
+    }
+}
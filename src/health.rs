@@ -0,0 +1,460 @@
+use super::Result;
+use eyre::eyre;
+use crate::notify::Notification;
+use crate::process_manager::{spawn_owned, GroupGuard};
+use nix::unistd::Pid;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tracing::{debug, info, warn};
+
+/// A check run against a managed process
+///
+/// Parsed from specs such as `tcp://127.0.0.1:8080`,
+/// `http://localhost:8080/healthz`, `exec:pg_isready -q` or `file:/tmp/ready`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Probe {
+    /// Succeeds when a TCP connection to `HOST:PORT` can be established
+    Tcp(String),
+    /// Succeeds when a plain HTTP GET returns a 2xx or 3xx status
+    Http {
+        /// `HOST:PORT` to connect to
+        authority: String,
+        /// Host header sent with the request
+        host: String,
+        /// Request path
+        path: String,
+    },
+    /// Succeeds when the shell command exits with status 0
+    Exec(String),
+    /// Succeeds when the file exists
+    File(PathBuf),
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Probe::Tcp(authority) => write!(f, "tcp://{}", authority),
+            Probe::Http { authority, path, .. } => write!(f, "http://{}{}", authority, path),
+            Probe::Exec(command) => write!(f, "exec:{}", command),
+            Probe::File(path) => write!(f, "file:{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for Probe {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // Hosts are resolved on every attempt, since they may only appear after startup
+        let check_authority = |authority: &str| -> Result<String> {
+            let (host, port) = authority
+                .rsplit_once(':')
+                .ok_or_else(|| eyre!("Invalid probe address '{}' in '{}': expected HOST:PORT", authority, s))?;
+            port.parse::<u16>()
+                .map_err(|e| eyre!("Invalid port '{}' in '{}': {}", port, s, e))?;
+            if host.is_empty() {
+                return Err(eyre!("Invalid probe address '{}' in '{}': missing host", authority, s));
+            }
+            Ok(authority.to_string())
+        };
+
+        if let Some(authority) = s.strip_prefix("tcp://") {
+            Ok(Probe::Tcp(check_authority(authority)?))
+        } else if let Some(rest) = s.strip_prefix("http://") {
+            let (authority, path) = match rest.find('/') {
+                Some(index) => rest.split_at(index),
+                None => (rest, "/"),
+            };
+            let host = authority.to_string();
+            // The port follows the last colon, unless that is inside an IPv6 literal
+            let authority = if authority.rsplit(']').next().unwrap_or(authority).contains(':') {
+                authority.to_string()
+            } else {
+                format!("{}:80", authority)
+            };
+            Ok(Probe::Http {
+                authority: check_authority(&authority)?,
+                host,
+                path: path.to_string(),
+            })
+        } else if let Some(command) = s.strip_prefix("exec:") {
+            if command.trim().is_empty() {
+                return Err(eyre!("Invalid probe '{}': missing command", s));
+            }
+            Ok(Probe::Exec(command.to_string()))
+        } else if let Some(path) = s.strip_prefix("file:") {
+            Ok(Probe::File(PathBuf::from(path)))
+        } else {
+            Err(eyre!(
+                "Invalid probe '{}': expected tcp://, http://, exec: or file:",
+                s
+            ))
+        }
+    }
+}
+
+impl Probe {
+    /// Runs the probe once
+    ///
+    /// # Arguments
+    /// * `timeout_duration` - Maximum time the probe may take
+    ///
+    /// # Returns
+    /// * `Result<()>` - Ok if the probe passed, otherwise the cause of the failure
+    pub async fn check(&self, timeout_duration: Duration) -> Result<()> {
+        match timeout(timeout_duration, self.run()).await {
+            Ok(result) => result,
+            Err(_) => Err(eyre!("timed out after {:?}", timeout_duration)),
+        }
+    }
+
+    async fn run(&self) -> Result<()> {
+        match self {
+            Probe::Tcp(authority) => {
+                connect(authority).await?;
+                Ok(())
+            }
+            Probe::Http { authority, host, path } => {
+                let mut stream = connect(authority).await?;
+                let request = format!(
+                    "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: scinit\r\nConnection: close\r\n\r\n",
+                    path, host
+                );
+                stream.write_all(request.as_bytes()).await?;
+
+                // The status line is all we need
+                let mut buf = [0u8; 64];
+                let mut len = 0;
+                while len < buf.len() {
+                    let n = stream.read(&mut buf[len..]).await?;
+                    if n == 0 {
+                        break;
+                    }
+                    len += n;
+                    if buf[..len].contains(&b'\n') {
+                        break;
+                    }
+                }
+                let status_line = String::from_utf8_lossy(&buf[..len]);
+                let status: u16 = status_line
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|code| code.parse().ok())
+                    .ok_or_else(|| eyre!("invalid HTTP response: {:?}", status_line.trim()))?;
+                if (200..400).contains(&status) {
+                    Ok(())
+                } else {
+                    Err(eyre!("HTTP status {}", status))
+                }
+            }
+            Probe::Exec(command) => {
+                // In its own process group, so a timeout also stops what the command started
+                let mut child = spawn_owned(Command::new("sh").arg("-c").arg(command).process_group(0).kill_on_drop(true))
+                    .map_err(|e| eyre!("failed to run '{}': {}", command, e))?;
+                let mut group = GroupGuard::new(&child);
+                let status = child
+                    .wait()
+                    .await
+                    .map_err(|e| eyre!("failed to wait for '{}': {}", command, e))?;
+                group.disarm();
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(eyre!("'{}' exited with {}", command, status))
+                }
+            }
+            Probe::File(path) => {
+                if tokio::fs::try_exists(path).await? {
+                    Ok(())
+                } else {
+                    Err(eyre!("{} does not exist", path.display()))
+                }
+            }
+        }
+    }
+}
+
+/// Connects to the first address `authority` resolves to that accepts a connection
+///
+/// # Arguments
+/// * `authority` - `HOST:PORT`, resolved anew on every call
+///
+/// # Returns
+/// * `Result<TcpStream>` - The connection, or the error of the last address tried
+async fn connect(authority: &str) -> Result<TcpStream> {
+    let addrs = tokio::net::lookup_host(authority)
+        .await
+        .map_err(|e| eyre!("resolving {} failed: {}", authority, e))?;
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(eyre!("connect to {} failed: {}", addr, e)),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| eyre!("{} did not resolve to any address", authority)))
+}
+
+/// Configuration for readiness detection
+#[derive(Debug, Clone)]
+pub struct ReadinessConfig {
+    /// Probe that passes once the process is ready, or None if readiness is
+    /// only reported over the notify socket
+    pub probe: Option<Probe>,
+    /// Time between probe attempts
+    pub interval: Duration,
+    /// Timeout for a single probe attempt
+    pub timeout: Duration,
+    /// How long a new process may take to become ready during an overlapping restart
+    pub startup_timeout: Duration,
+}
+
+/// Configuration for liveness checking
+#[derive(Debug, Clone)]
+pub struct LivenessConfig {
+    /// Probe that keeps passing while the process is healthy
+    pub probe: Probe,
+    /// Time between probe attempts
+    pub interval: Duration,
+    /// Timeout for a single probe attempt
+    pub timeout: Duration,
+    /// Consecutive failures after which the process is considered hung
+    pub failure_threshold: u32,
+    /// Grace period after spawn before the first probe
+    pub start_period: Duration,
+}
+
+/// Health events reported by background probe tasks
+#[derive(Debug, Clone, PartialEq)]
+pub enum HealthEvent {
+    /// The process with the given PID passed its readiness probe
+    Ready(Pid),
+    /// The process with the given PID failed its liveness probe too many times in a row
+    Unhealthy(Pid),
+    /// The process with the given PID sent a message on the notify socket
+    Notify(Pid, Notification),
+}
+
+/// Spawns a task that probes until the process is ready, then reports it
+///
+/// # Arguments
+/// * `config` - Readiness configuration
+/// * `pid` - PID of the process being probed, included in the event
+/// * `events` - Channel the `Ready` event is sent on
+///
+/// # Returns
+/// * `JoinHandle<()>` - Handle used to cancel the probe when the process goes away
+pub fn spawn_readiness_probe(
+    config: ReadinessConfig,
+    pid: Pid,
+    events: mpsc::UnboundedSender<HealthEvent>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let Some(probe) = config.probe else {
+            return;
+        };
+        let mut attempts = 0u32;
+        loop {
+            attempts += 1;
+            match probe.check(config.timeout).await {
+                Ok(()) => {
+                    info!("Readiness probe {} passed for process {} after {} attempts", probe, pid, attempts);
+                    let _ = events.send(HealthEvent::Ready(pid));
+                    return;
+                }
+                Err(e) => {
+                    debug!("Readiness probe {} for process {} not passing yet: {}", probe, pid, e);
+                }
+            }
+            sleep(config.interval).await;
+        }
+    })
+}
+
+/// Spawns a task that probes periodically and reports when the process stops responding
+///
+/// Every failure is logged with its cause. The task ends after reporting
+/// `Unhealthy`, since the process is expected to be restarted.
+///
+/// # Arguments
+/// * `config` - Liveness configuration
+/// * `pid` - PID of the process being probed, included in the event
+/// * `events` - Channel the `Unhealthy` event is sent on
+///
+/// # Returns
+/// * `JoinHandle<()>` - Handle used to cancel the probe when the process goes away
+pub fn spawn_liveness_probe(
+    config: LivenessConfig,
+    pid: Pid,
+    events: mpsc::UnboundedSender<HealthEvent>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        sleep(config.start_period).await;
+
+        let threshold = config.failure_threshold.max(1);
+        let mut failures = 0u32;
+        loop {
+            match config.probe.check(config.timeout).await {
+                Ok(()) => {
+                    if failures > 0 {
+                        info!("Liveness probe {} for process {} recovered after {} failures", config.probe, pid, failures);
+                    }
+                    failures = 0;
+                }
+                Err(e) => {
+                    failures += 1;
+                    warn!(
+                        "Liveness probe {} for process {} failed ({}/{}): {}",
+                        config.probe, pid, failures, threshold, e
+                    );
+                    if failures >= threshold {
+                        let _ = events.send(HealthEvent::Unhealthy(pid));
+                        return;
+                    }
+                }
+            }
+            sleep(config.interval).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_probe_parsing() {
+        assert_eq!(
+            "tcp://127.0.0.1:8080".parse::<Probe>().unwrap(),
+            Probe::Tcp("127.0.0.1:8080".to_string())
+        );
+        // Names that don't resolve yet are fine until the probe runs
+        assert_eq!(
+            "tcp://db.invalid:5432".parse::<Probe>().unwrap(),
+            Probe::Tcp("db.invalid:5432".to_string())
+        );
+        match "http://[::1]/healthz".parse::<Probe>().unwrap() {
+            Probe::Http { authority, .. } => assert_eq!(authority, "[::1]:80"),
+            other => panic!("unexpected probe {:?}", other),
+        }
+        match "http://localhost:8080/healthz".parse::<Probe>().unwrap() {
+            Probe::Http { authority, host, path } => {
+                assert_eq!(authority, "localhost:8080");
+                assert_eq!(host, "localhost:8080");
+                assert_eq!(path, "/healthz");
+            }
+            other => panic!("unexpected probe {:?}", other),
+        }
+        assert_eq!(
+            "exec:test -e /tmp".parse::<Probe>().unwrap(),
+            Probe::Exec("test -e /tmp".to_string())
+        );
+        assert_eq!(
+            "file:/tmp/ready".parse::<Probe>().unwrap(),
+            Probe::File(PathBuf::from("/tmp/ready"))
+        );
+        assert!("grpc://localhost:1".parse::<Probe>().is_err());
+        assert!("tcp://localhost".parse::<Probe>().is_err());
+        assert!("tcp://:80".parse::<Probe>().is_err());
+        assert!("exec: ".parse::<Probe>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_and_http_probes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for status in ["200 OK", "503 Service Unavailable"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 256];
+                let _ = stream.read(&mut buf).await;
+                let response = format!("HTTP/1.0 {}\r\n\r\n", status);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let http = Probe::Http { authority: addr.to_string(), host: "localhost".to_string(), path: "/".to_string() };
+        assert!(http.check(Duration::from_secs(1)).await.is_ok());
+        let err = http.check(Duration::from_secs(1)).await.unwrap_err();
+        assert!(err.to_string().contains("503"));
+
+        // Every address the name resolves to is tried, whether ::1 comes first or not
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let probe = Probe::Tcp(format!("localhost:{}", listener.local_addr().unwrap().port()));
+        assert!(probe.check(Duration::from_secs(1)).await.is_ok());
+        drop(listener);
+        assert!(probe.check(Duration::from_secs(1)).await.is_err());
+
+        let err = Probe::Tcp("db.invalid:5432".to_string()).check(Duration::from_secs(5)).await.unwrap_err();
+        assert!(err.to_string().contains("resolving"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_exec_and_file_probes() {
+        assert!(Probe::Exec("true".to_string()).check(Duration::from_secs(1)).await.is_ok());
+        assert!(Probe::Exec("exit 3".to_string()).check(Duration::from_secs(1)).await.is_err());
+        let err = Probe::Exec("sleep 5".to_string())
+            .check(Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("ready");
+        assert!(Probe::File(path.clone()).check(Duration::from_secs(1)).await.is_err());
+        std::fs::write(&path, "").unwrap();
+        assert!(Probe::File(path).check(Duration::from_secs(1)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_exec_probe_timeout_kills_its_children() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pid_file = temp_dir.path().join("pid");
+        let command = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        assert!(Probe::Exec(command).check(Duration::from_millis(300)).await.is_err());
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap().trim().to_string();
+        let stat = format!("/proc/{}/stat", pid);
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        // Gone, or a zombie waiting for whoever adopted it
+        while std::fs::read_to_string(&stat).is_ok_and(|stat| !stat.contains(") Z ")) {
+            assert!(std::time::Instant::now() < deadline, "background sleep {} survived the probe", pid);
+            sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_liveness_probe_threshold() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("alive");
+        std::fs::write(&path, "").unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let config = LivenessConfig {
+            probe: Probe::File(path.clone()),
+            interval: Duration::from_millis(20),
+            timeout: Duration::from_secs(1),
+            failure_threshold: 3,
+            start_period: Duration::from_millis(10),
+        };
+        let pid = Pid::from_raw(4242);
+        let task = spawn_liveness_probe(config, pid, tx);
+
+        // Healthy: nothing is reported
+        assert!(timeout(Duration::from_millis(150), rx.recv()).await.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        let event = timeout(Duration::from_secs(2), rx.recv()).await.unwrap();
+        assert_eq!(event, Some(HealthEvent::Unhealthy(pid)));
+        task.await.unwrap();
+    }
+}
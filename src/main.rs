@@ -1,27 +1,50 @@
 type Result<T> = color_eyre::eyre::Result<T>;
 
+mod cgroups;
 mod cli;
+mod config_file;
+mod env;
 mod file_watcher;
+mod health;
+mod notify;
 mod port_manager;
 mod process_manager;
+mod restart_policy;
+mod rlimits;
 mod signals;
+mod socket_activation;
+mod supervisor;
+mod users;
 
-use clap::Parser;
-use std::collections::HashMap;
+use clap::{CommandFactory, FromArgMatches};
 use std::time::Duration;
 use tokio::select;
 use tokio::time::interval;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-use cli::{Cli, Config};
-use file_watcher::{FileWatcher, handle_file_events};
-use port_manager::PortManager;
-use process_manager::{ProcessConfig, ProcessManager, process_group_to_foreground, handle_child_exit, reap_zombies_async};
+use cgroups::CgroupConfig;
+use cli::{Action, Cli, Config};
+use env::EnvConfig;
+use file_watcher::{FileChangeEvent, FileWatcher, handle_file_event};
+use port_manager::PortBindingConfig;
+use restart_policy::RestartPolicyConfig;
+use process_manager::{ProcessConfig, ProcessEvent, RestartMode, ProcessState, process_group_to_foreground, exit_code_from_status, reap_zombies_async, setup_reaping};
 use signals::{SignalHandler, SignalAction};
+use supervisor::{ServiceConfig, Supervisor, SupervisorAction, PRIMARY_SERVICE};
+use users::Credentials;
+
+fn main() -> Result<()> {
+    // Block forwarded signals before tokio spawns its worker threads so they inherit the mask
+    signals::block_forwarded_signals()?;
+
+    let exit_code = run()?;
+    // Exit outside the runtime so every destructor has already run
+    std::process::exit(exit_code);
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn run() -> Result<i32> {
     // Initialize error handling and logging
     color_eyre::install()?;
 
@@ -31,30 +54,113 @@ async fn main() -> Result<()> {
         .init();
 
     // Note: We don't create a separate process group for scinit to allow Ctrl+C during development
-    // Signal masking is handled when SignalHandler is created (pthread_sigmask affects only calling thread)
+    // Forwarded signals are already blocked process-wide (see main); SignalHandler adds SIGCHLD for this thread
 
     info!("scinit starting");
 
     // Parse CLI arguments
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    let action = cli.action.clone();
+
+    // Convert CLI and config file to configuration
+    let config = Config::load(cli, &matches)?;
 
-    // Convert CLI to configuration
-    let config = Config::from_cli(cli)?;
+    if action == Some(Action::CheckConfig) {
+        println!("{:#?}", config);
+        return Ok(0);
+    }
+
+    // Make sure orphaned descendants are reaped by us even when we're not PID 1
+    setup_reaping(config.subreaper)?;
+
+    // Every service gets its own cgroup below the delegated one
+    let cgroup_root = match config.cgroup {
+        Some(ref cgroup) => Some(cgroups::prepare_root(config.cgroup_root.as_deref(), &cgroup.controllers())?),
+        None => None,
+    };
+    let service_cgroup = |name: &str| {
+        config.cgroup.clone().zip(cgroup_root.as_ref()).map(|(cgroup, root)| CgroupConfig {
+            path: root.join(name),
+            ..cgroup
+        })
+    };
 
     // Setup components
-    let port_manager = PortManager::new(config.port_binding.clone());
-    
     let process_config = ProcessConfig {
         command: config.command.clone(),
         args: config.args.clone(),
         restart_delay: Duration::from_millis(config.live_reload.restart_delay_ms),
         graceful_shutdown_timeout: Duration::from_secs(config.live_reload.graceful_timeout_secs),
-        working_directory: None,
-        environment: HashMap::new(),
+        working_directory: config.working_directory.clone(),
+        env: config.env.clone(),
+        environment: config.environment.clone(),
+        restart_policy: config.restart_policy.clone(),
+        restart_mode: config.live_reload.restart_mode,
+        readiness: config.readiness.clone(),
+        liveness: config.liveness.clone(),
+        notify: config.notify.clone(),
+        credentials: config.credentials.clone(),
+        rlimits: config.rlimits.clone(),
+        cgroup: None,
+        stop_signal: config.stop_signal,
+        stop_target: config.stop_target,
     };
     
-    let mut process_manager = ProcessManager::new(process_config, port_manager);
-    let mut signal_handler = SignalHandler::new()?;
+    // The main command is the primary service; extra services fall back to its settings
+    let mut services = vec![ServiceConfig {
+        name: PRIMARY_SERVICE.to_string(),
+        process: ProcessConfig {
+            cgroup: service_cgroup(PRIMARY_SERVICE),
+            ..process_config.clone()
+        },
+        ports: config.port_binding.clone(),
+        on_exit: config.service_exit_policy,
+    }];
+    for spec in &config.services {
+        let stop_signal = spec.stop_signal.unwrap_or(config.stop_signal);
+        services.push(ServiceConfig {
+            name: spec.name.clone(),
+            process: ProcessConfig {
+                command: spec.command.clone(),
+                args: spec.args.clone(),
+                working_directory: spec.working_directory.clone().or_else(|| config.working_directory.clone()),
+                env: EnvConfig {
+                    files: spec.env_files.clone().unwrap_or_else(|| config.env.files.clone()),
+                    ..config.env.clone()
+                },
+                environment: config.environment.clone().into_iter().chain(spec.environment.clone()).collect(),
+                restart_policy: RestartPolicyConfig {
+                    policy: spec.restart.unwrap_or(config.restart_policy.policy),
+                    ..config.restart_policy.clone()
+                },
+                // Overlapping needs a readiness signal, which sidecars don't have
+                restart_mode: spec.restart_mode.unwrap_or(RestartMode::StopStart),
+                readiness: None,
+                liveness: None,
+                notify: None,
+                credentials: match (&spec.user, &spec.group) {
+                    (None, None) => config.credentials.clone(),
+                    (user, group) => Credentials::resolve(user.as_deref(), group.as_deref())?,
+                },
+                rlimits: spec.rlimits.clone().unwrap_or_else(|| config.rlimits.clone()),
+                cgroup: service_cgroup(&spec.name),
+                stop_signal,
+                stop_target: config.signal_policy.target(stop_signal as i32),
+                ..process_config.clone()
+            },
+            ports: PortBindingConfig {
+                ports: spec.ports.clone(),
+                listeners: spec.listeners.clone(),
+                bind_address: config.port_binding.bind_address,
+                ..PortBindingConfig::default()
+            },
+            on_exit: spec.on_exit.unwrap_or(config.service_exit_policy),
+        });
+    }
+
+    let mut supervisor = Supervisor::new(services)?;
+    let mut signal_handler = SignalHandler::new(config.signal_policy.clone())?;
 
     // Create file watcher if live-reload is enabled
     let mut file_watcher = if let Some(watch_config) = config.file_watch_config() {
@@ -64,19 +170,19 @@ async fn main() -> Result<()> {
     };
 
     // Run the main event loop
-    run_main_loop(config, &mut process_manager, &mut signal_handler, &mut file_watcher).await?;
+    let exit_code = run_main_loop(config, &mut supervisor, &mut signal_handler, &mut file_watcher).await?;
 
-    info!("scinit exiting");
-    Ok(())
+    info!("scinit exiting with code {}", exit_code);
+    Ok(exit_code)
 }
 
 /// Main event loop orchestration
 async fn run_main_loop(
     config: Config,
-    process_manager: &mut ProcessManager,
+    supervisor: &mut Supervisor,
     signal_handler: &mut SignalHandler, 
     file_watcher: &mut Option<FileWatcher>
-) -> Result<()> {
+) -> Result<i32> {
     let mut zombie_reap_interval = interval(config.zombie_reap_interval);
 
     info!("init system started, managing subprocess: {}", config.command);
@@ -89,57 +195,81 @@ async fn run_main_loop(
         debug!("Live-reload disabled, no file watching");
     }
 
-    // Spawn initial process
-    process_manager.spawn_process().await?;
+    // Spawn initial processes
+    supervisor.spawn_all().await?;
     
-    // Setup process group
-    if let Some(pid) = process_manager.process_info().pid {
+    // Setup process group of the primary service
+    if let Some(pid) = supervisor.primary().process_info().pgid {
         use nix::unistd::getpgid;
         let pgid = getpgid(Some(pid))?;
         tokio::task::spawn_blocking(move || process_group_to_foreground(pgid)).await??;
     }
 
     loop {
-        // Check for file events first (if enabled)
-        if file_watcher.is_some()
-            && handle_file_events(file_watcher, process_manager).await? {
-            return Ok(()); // Exit requested
-        }
+        let restart_at = supervisor.pending_restart();
 
         select! {
-            // Check if subprocess has exited
-            exit_status = process_manager.wait_for_exit() => {
-                match exit_status {
-                    Ok(Some(status)) => {
-                        // Scenario A: Child process exit handling
-                        handle_child_exit(status).await?;
-                        return Ok(());
+            // Check if a subprocess has exited or changed state
+            (index, event) = supervisor.next_event(), if supervisor.has_children() => {
+                let action = match event {
+                    Ok(ProcessEvent::Ready(pid)) => {
+                        debug!("service {} process {} reported ready", supervisor.name(index), pid);
+                        SupervisorAction::Continue
                     }
-                    Ok(None) => {
-                        // No process to wait for, continue
-                        continue;
+                    Ok(ProcessEvent::Unhealthy(pid)) => {
+                        warn!("service {} process {} is unhealthy", supervisor.name(index), pid);
+                        supervisor.handle_unhealthy(index).await?
                     }
+                    // Scenario A: Child process exit handling, unless the restart policy takes over
+                    Ok(ProcessEvent::Exited(status)) => supervisor.handle_exit(index, status).await?,
                     Err(e) => {
-                        error!("error waiting for subprocess: {}", e);
+                        error!("error waiting for service {}: {}", supervisor.name(index), e);
+                        supervisor.shutdown_all().await;
                         return Err(e);
                     }
+                };
+                if let Some(code) = exit_code_for(&config, action) {
+                    return Ok(code);
                 }
             }
 
             // Synchronous signal handling - proper for init systems
-            signal = signal_handler.wait_for_signal(config.signal_poll_interval) => {
-                match signal? {
-                    Some(signal) => {
-                        info!("received signal: {:?}", signal);
-                        match signal_handler.process_signal(signal, process_manager, config.live_reload.graceful_timeout_secs).await? {
-                            SignalAction::Exit => return Ok(()),
-                            SignalAction::ReapZombies => reap_zombies_async().await,
-                            SignalAction::Continue => {},
+            signal = signal_handler.next_signal() => {
+                let signal = signal?;
+                info!("received signal: {}", signals::signal_name(signal));
+                match signal_handler.process_signal(signal, supervisor, config.live_reload.graceful_timeout_secs).await? {
+                    SignalAction::Exit => {
+                        // A child that never reported a status is treated as killed by the signal
+                        return Ok(child_exit_code(&config, supervisor, 128 + signal));
+                    }
+                    SignalAction::Restart(signal) => {
+                        let action = supervisor.restart_all(signal, config.signal_policy.restart_mode).await?;
+                        if let Some(code) = exit_code_for(&config, action) {
+                            return Ok(code);
                         }
                     }
-                    None => {
-                        // No signal received, continue
+                    SignalAction::ReapZombies => reap_zombies_async().await,
+                    SignalAction::Continue => {},
+                }
+            }
+
+            // Watched files changed and have been quiet for the debounce time
+            Some(event) = next_file_event(file_watcher) => {
+                if handle_file_event(event, supervisor.primary_mut()).await? {
+                    // Exit requested, because the restart wasn't allowed or used up the budget
+                    let exhausted = supervisor.primary().process_info().state == ProcessState::Failed;
+                    supervisor.shutdown_all().await;
+                    if exhausted {
+                        return Ok(config.exhausted_exit_code());
                     }
+                    return Ok(child_exit_code(&config, supervisor, 0));
+                }
+            }
+
+            // Scheduled crash restart is due
+            _ = sleep_until_deadline(restart_at), if restart_at.is_some() => {
+                if let Some(code) = exit_code_for(&config, supervisor.restart_due().await?) {
+                    return Ok(code);
                 }
             }
 
@@ -149,4 +279,36 @@ async fn run_main_loop(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Exit code for scinit if the supervisor has stopped all services
+fn exit_code_for(config: &Config, action: SupervisorAction) -> Option<i32> {
+    match action {
+        SupervisorAction::Continue => None,
+        SupervisorAction::Exit { code, oom_killed } => Some(config.map_exit_code(code, oom_killed)),
+        SupervisorAction::Exhausted => Some(config.exhausted_exit_code()),
+    }
+}
+
+/// Waits for the next file change event, or forever if live-reload is disabled
+async fn next_file_event(file_watcher: &mut Option<FileWatcher>) -> Option<FileChangeEvent> {
+    match file_watcher {
+        Some(file_watcher) => file_watcher.next_event().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Sleeps until the deadline, or forever if there is none
+async fn sleep_until_deadline(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Exit code for scinit derived from the primary service's last exit status, after remapping
+fn child_exit_code(config: &Config, supervisor: &Supervisor, fallback: i32) -> i32 {
+    let info = supervisor.primary().process_info();
+    let code = info.exit_status.map(exit_code_from_status).unwrap_or(fallback);
+    config.map_exit_code(code, info.oom_killed)
+}
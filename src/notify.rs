@@ -0,0 +1,333 @@
+use super::Result;
+use crate::health::HealthEvent;
+use crate::port_manager::remove_stale_socket;
+use eyre::eyre;
+use nix::sys::socket::{recvmsg, setsockopt, sockopt, ControlMessageOwned, MsgFlags, UnixAddr};
+use nix::unistd::Pid;
+use socket2::Type;
+use std::fmt;
+use std::io::IoSliceMut;
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::Interest;
+use tokio::net::UnixDatagram;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Largest datagram accepted on the notify socket
+const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// Where the notify socket is bound
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotifyAddress {
+    /// Linux abstract socket name, written as `@name`
+    Abstract(String),
+    /// Socket file on the filesystem
+    Path(PathBuf),
+}
+
+impl NotifyAddress {
+    /// Default address: an abstract socket unique to this scinit instance
+    pub fn default_for_current_process() -> Self {
+        NotifyAddress::Abstract(format!("scinit/notify/{}", std::process::id()))
+    }
+}
+
+impl fmt::Display for NotifyAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifyAddress::Abstract(name) => write!(f, "@{}", name),
+            NotifyAddress::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for NotifyAddress {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() || s == "@" {
+            return Err(eyre!("Invalid notify socket address '{}'", s));
+        }
+        match s.strip_prefix('@') {
+            Some(name) => Ok(NotifyAddress::Abstract(name.to_string())),
+            None => Ok(NotifyAddress::Path(PathBuf::from(s))),
+        }
+    }
+}
+
+/// Configuration for the sd_notify socket
+#[derive(Debug, Clone)]
+pub struct NotifyConfig {
+    /// Address the socket is bound to and passed as `NOTIFY_SOCKET`
+    pub address: NotifyAddress,
+    /// Watchdog interval passed as `WATCHDOG_USEC`, if the watchdog is enabled
+    pub watchdog: Option<Duration>,
+}
+
+/// A single assignment from an sd_notify message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification {
+    /// `READY=1`: startup finished
+    Ready,
+    /// `STATUS=...`: free-form status text
+    Status(String),
+    /// `MAINPID=...`: the service's main process
+    MainPid(Pid),
+    /// `WATCHDOG=1`: keep-alive ping
+    Watchdog,
+    /// `WATCHDOG=trigger`: the service asks to be treated as hung
+    WatchdogTrigger,
+    /// `WATCHDOG_USEC=...`: new watchdog interval
+    WatchdogUsec(Duration),
+    /// `STOPPING=1`: the service is shutting down
+    Stopping,
+}
+
+/// Parses an sd_notify datagram into the notifications scinit understands
+///
+/// Unknown or malformed assignments are skipped.
+///
+/// # Arguments
+/// * `data` - Newline-separated `KEY=VALUE` assignments
+///
+/// # Returns
+/// * `Vec<Notification>` - Recognized notifications in message order
+pub fn parse_message(data: &[u8]) -> Vec<Notification> {
+    let text = String::from_utf8_lossy(data);
+    text.lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            let notification = match (key, value) {
+                ("READY", "1") => Notification::Ready,
+                ("STOPPING", "1") => Notification::Stopping,
+                ("STATUS", status) => Notification::Status(status.to_string()),
+                ("MAINPID", pid) => Notification::MainPid(Pid::from_raw(pid.parse().ok().filter(|&pid| pid > 0)?)),
+                ("WATCHDOG", "1") => Notification::Watchdog,
+                ("WATCHDOG", "trigger") => Notification::WatchdogTrigger,
+                ("WATCHDOG_USEC", usec) => Notification::WatchdogUsec(Duration::from_micros(usec.parse().ok().filter(|&usec| usec > 0)?)),
+                _ => {
+                    debug!("Ignoring notify assignment {:?}", line);
+                    return None;
+                }
+            };
+            Some(notification)
+        })
+        .collect()
+}
+
+/// Datagram socket children report their state on, as with systemd's `NOTIFY_SOCKET`
+///
+/// Incoming messages are parsed and forwarded as `HealthEvent::Notify`,
+/// tagged with the sender's PID taken from its socket credentials.
+pub struct NotifySocket {
+    /// Address the socket is bound to
+    address: NotifyAddress,
+    /// Task receiving messages
+    task: JoinHandle<()>,
+}
+
+impl NotifySocket {
+    /// Binds the socket and starts receiving messages
+    ///
+    /// A stale socket at a filesystem address is replaced, but a socket a
+    /// running process is bound to, or any other file there, is left alone. Relative paths are made absolute, since the
+    /// child may run in a different working directory.
+    ///
+    /// # Arguments
+    /// * `address` - Where to bind the socket
+    /// * `events` - Channel notifications are sent on
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The bound socket or an error
+    pub fn bind(address: NotifyAddress, events: mpsc::UnboundedSender<HealthEvent>) -> Result<Self> {
+        let address = match address {
+            NotifyAddress::Path(path) => NotifyAddress::Path(
+                std::path::absolute(&path).map_err(|e| eyre!("Invalid notify socket path {:?}: {}", path, e))?,
+            ),
+            address => address,
+        };
+        let socket = match address {
+            NotifyAddress::Abstract(ref name) => {
+                use std::os::linux::net::SocketAddrExt;
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+                std::os::unix::net::UnixDatagram::bind_addr(&addr)
+            }
+            NotifyAddress::Path(ref path) => {
+                // Only a socket left behind by a previous run is replaced
+                remove_stale_socket(path, Type::DGRAM)
+                    .map_err(|e| eyre!("Failed to bind notify socket {}: {}", address, e))?;
+                std::os::unix::net::UnixDatagram::bind(path)
+            }
+        }
+        .map_err(|e| eyre!("Failed to bind notify socket {}: {}", address, e))?;
+
+        // The kernel then attaches the sender's credentials to every message
+        setsockopt(&socket, sockopt::PassCred, &true)?;
+        socket.set_nonblocking(true)?;
+        let socket = UnixDatagram::from_std(socket)?;
+
+        debug!("Listening for notifications on {}", address);
+        let task = tokio::spawn(receive_loop(socket, events));
+        Ok(Self { address, task })
+    }
+
+    /// Value for the child's `NOTIFY_SOCKET` environment variable
+    pub fn env_value(&self) -> String {
+        self.address.to_string()
+    }
+}
+
+impl Drop for NotifySocket {
+    fn drop(&mut self) {
+        self.task.abort();
+        if let NotifyAddress::Path(ref path) = self.address {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Receives datagrams until the event channel closes
+async fn receive_loop(socket: UnixDatagram, events: mpsc::UnboundedSender<HealthEvent>) {
+    let mut buf = vec![0u8; MAX_MESSAGE_SIZE];
+    loop {
+        let received = socket
+            .async_io(Interest::READABLE, || receive_message(socket.as_raw_fd(), &mut buf))
+            .await;
+        let (len, sender) = match received {
+            Ok(received) => received,
+            Err(e) => {
+                warn!("Failed to receive on notify socket: {}", e);
+                continue;
+            }
+        };
+        let Some(sender) = sender else {
+            warn!("Ignoring notification without sender credentials");
+            continue;
+        };
+
+        for notification in parse_message(&buf[..len]) {
+            if events.send(HealthEvent::Notify(sender, notification)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Reads one datagram along with the sender's PID
+///
+/// File descriptors passed along with the message are closed, since scinit
+/// doesn't keep a file descriptor store.
+fn receive_message(fd: std::os::fd::RawFd, buf: &mut [u8]) -> std::io::Result<(usize, Option<Pid>)> {
+    let mut cmsg = nix::cmsg_space!(libc::ucred, [std::os::fd::RawFd; 16]);
+    let mut iov = [IoSliceMut::new(buf)];
+    let msg = recvmsg::<UnixAddr>(fd, &mut iov, Some(&mut cmsg), MsgFlags::MSG_CMSG_CLOEXEC)?;
+
+    let mut sender = None;
+    for cmsg in msg.cmsgs()? {
+        match cmsg {
+            ControlMessageOwned::ScmCredentials(creds) => sender = Some(Pid::from_raw(creds.pid())),
+            ControlMessageOwned::ScmRights(fds) => {
+                for fd in fds {
+                    let _ = nix::unistd::close(fd);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((msg.bytes, sender))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message() {
+        let message = b"READY=1\nSTATUS=Serving 3 clients\nMAINPID=1234\nWATCHDOG=1\nBOGUS=1\nMAINPID=abc\nWATCHDOG_USEC=500000\nSTOPPING=1";
+        assert_eq!(
+            parse_message(message),
+            vec![
+                Notification::Ready,
+                Notification::Status("Serving 3 clients".to_string()),
+                Notification::MainPid(Pid::from_raw(1234)),
+                Notification::Watchdog,
+                Notification::WatchdogUsec(Duration::from_millis(500)),
+                Notification::Stopping,
+            ]
+        );
+        assert_eq!(parse_message(b"WATCHDOG=trigger"), vec![Notification::WatchdogTrigger]);
+        assert!(parse_message(b"READY=0").is_empty());
+    }
+
+    #[test]
+    fn test_address_parsing() {
+        assert_eq!("@scinit".parse::<NotifyAddress>().unwrap(), NotifyAddress::Abstract("scinit".to_string()));
+        assert_eq!(
+            "/run/scinit/notify".parse::<NotifyAddress>().unwrap(),
+            NotifyAddress::Path(PathBuf::from("/run/scinit/notify"))
+        );
+        assert!("@".parse::<NotifyAddress>().is_err());
+        assert_eq!(NotifyAddress::Abstract("x".to_string()).to_string(), "@x");
+    }
+
+    #[tokio::test]
+    async fn test_receives_with_sender_pid() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("notify.sock");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let socket = NotifySocket::bind(NotifyAddress::Path(path.clone()), tx).unwrap();
+        assert_eq!(socket.env_value(), path.display().to_string());
+
+        let client = std::os::unix::net::UnixDatagram::unbound().unwrap();
+        client.send_to(b"READY=1\nSTATUS=up", &path).unwrap();
+
+        let me = Pid::this();
+        assert_eq!(rx.recv().await, Some(HealthEvent::Notify(me, Notification::Ready)));
+        assert_eq!(
+            rx.recv().await,
+            Some(HealthEvent::Notify(me, Notification::Status("up".to_string())))
+        );
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_bind_replaces_only_sockets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("notify.sock");
+        std::fs::write(&path, "data").unwrap();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        assert!(NotifySocket::bind(NotifyAddress::Path(path.clone()), tx.clone()).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "data");
+
+        // A socket another process is still bound to is left alone
+        std::fs::remove_file(&path).unwrap();
+        let live = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+        assert!(NotifySocket::bind(NotifyAddress::Path(path.clone()), tx.clone()).is_err());
+        let sender = std::os::unix::net::UnixDatagram::unbound().unwrap();
+        sender.send_to(b"READY=1", &path).unwrap();
+        let mut buf = [0u8; 16];
+        assert_eq!(live.recv(&mut buf).unwrap(), 7);
+
+        drop(live);
+        let socket = NotifySocket::bind(NotifyAddress::Path(path.clone()), tx).unwrap();
+        drop(socket);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_relative_path_is_made_absolute() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let name = format!("scinit-notify-test-{}.sock", std::process::id());
+        let socket = NotifySocket::bind(NotifyAddress::Path(PathBuf::from(&name)), tx).unwrap();
+        let expected = std::env::current_dir().unwrap().join(&name);
+        assert_eq!(socket.env_value(), expected.display().to_string());
+        drop(socket);
+        assert!(!expected.exists());
+    }
+}
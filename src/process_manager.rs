@@ -1,21 +1,36 @@
 use super::Result;
-use crate::port_manager::PortManager;
-use crate::signals::signal_name;
+use crate::cgroups::{oom_kill_count, Cgroup, CgroupConfig, ServiceCgroup};
+use crate::env::EnvConfig;
+use crate::health::{spawn_liveness_probe, spawn_readiness_probe, HealthEvent, LivenessConfig, ReadinessConfig};
+use crate::notify::{Notification, NotifyConfig, NotifySocket};
+use crate::port_manager::{inherit_in_child, PortManager};
+use crate::socket_activation::SocketActivation;
+use crate::rlimits::RlimitSpec;
+use crate::restart_policy::{RestartDecision, RestartPolicy, RestartPolicyConfig, RestartTracker};
+use crate::signals::{signal_name, SignalTarget};
+use crate::users::Credentials;
 use eyre::eyre;
-use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::sys::wait::{waitid, waitpid, Id, WaitPidFlag, WaitStatus};
 use nix::unistd::{getpgid, tcsetpgrp, Pid};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::IsTerminal;
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use std::time::Duration;
 use tokio::process::{Child, Command};
+use tokio::select;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info, warn};
 
 use super::signals::Signal;
 
+/// Shortest delay before retrying a failed start after a file change
+const MIN_SPAWN_RETRY_DELAY: Duration = Duration::from_millis(100);
+
 /// Configuration for process management behavior
 #[derive(Debug, Clone)]
 pub struct ProcessConfig {
@@ -23,14 +38,37 @@ pub struct ProcessConfig {
     pub command: String,
     /// Arguments for the command
     pub args: Vec<String>,
-    /// Delay before restart after graceful shutdown
+    /// Delay before restart after graceful shutdown; in overlap mode, how long
+    /// the new process must stay up before the old one is stopped
     pub restart_delay: Duration,
     /// Timeout for graceful shutdown
     pub graceful_shutdown_timeout: Duration,
     /// Working directory for the process
     pub working_directory: Option<PathBuf>,
+    /// How the inherited environment is filtered and which env files are loaded
+    pub env: EnvConfig,
     /// Environment variables to set
     pub environment: HashMap<String, String>,
+    /// Policy for restarting the process after it exits on its own
+    pub restart_policy: RestartPolicyConfig,
+    /// How a running process is replaced on restart
+    pub restart_mode: RestartMode,
+    /// Readiness probe for the process, if any
+    pub readiness: Option<ReadinessConfig>,
+    /// Liveness probe for the process, if any
+    pub liveness: Option<LivenessConfig>,
+    /// sd_notify socket offered to the process, if any
+    pub notify: Option<NotifyConfig>,
+    /// Identity the process runs as, if not scinit's own
+    pub credentials: Option<Credentials>,
+    /// Resource limits set on the process
+    pub rlimits: Vec<RlimitSpec>,
+    /// cgroup the process tree is placed in, if any
+    pub cgroup: Option<CgroupConfig>,
+    /// Signal sent to stop the process gracefully
+    pub stop_signal: Signal,
+    /// Whether the stop signal goes to the whole process group or the main process only
+    pub stop_target: SignalTarget,
 }
 
 impl Default for ProcessConfig {
@@ -41,11 +79,59 @@ impl Default for ProcessConfig {
             restart_delay: Duration::from_millis(1000),
             graceful_shutdown_timeout: Duration::from_secs(30),
             working_directory: None,
+            env: EnvConfig::default(),
             environment: HashMap::new(),
+            restart_policy: RestartPolicyConfig::default(),
+            restart_mode: RestartMode::StopStart,
+            readiness: None,
+            liveness: None,
+            notify: None,
+            credentials: None,
+            rlimits: Vec::new(),
+            cgroup: None,
+            stop_signal: Signal::SIGTERM,
+            stop_target: SignalTarget::Group,
         }
     }
 }
 
+/// How file-change and crash restarts replace the running process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RestartMode {
+    /// Stop the old process, wait the restart delay, then start the new one
+    StopStart,
+    /// Start the new process on the same sockets and stop the old one once it is ready
+    Overlap,
+}
+
+/// Why a process is being restarted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartReason {
+    /// Watched files changed
+    FileChange,
+    /// The process exited on its own
+    Crash,
+    /// The process was killed by the OOM killer
+    Oom,
+    /// The operator asked for a restart with this signal
+    Signal(Signal),
+    /// A start after a file change failed and is being retried
+    SpawnRetry,
+}
+
+/// What came of a request to restart the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartOutcome {
+    /// A new process was started, or a retry of its failed start was scheduled
+    Restarted,
+    /// The restart policy doesn't allow this restart, or the manager is stopping
+    NotAllowed,
+    /// The running process was kept because the new one couldn't be prepared
+    Kept,
+    /// Retrying a failed start used up the restart budget; the manager is `Failed`
+    Exhausted,
+}
+
 /// State of a managed process
 #[derive(Debug, Clone, PartialEq)]
 pub enum ProcessState {
@@ -53,6 +139,8 @@ pub enum ProcessState {
     Starting,
     /// Process is running
     Running,
+    /// Process is running and passed its readiness probe
+    Ready,
     /// Process is stopping (graceful shutdown)
     Stopping,
     /// Process has stopped
@@ -61,17 +149,41 @@ pub enum ProcessState {
     Failed,
 }
 
+/// Events reported by `ProcessManager::next_event`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessEvent {
+    /// The current process exited
+    Exited(std::process::ExitStatus),
+    /// The current process became ready
+    Ready(Pid),
+    /// The current process failed its liveness probe
+    Unhealthy(Pid),
+}
+
+/// What woke up `next_event`
+enum Wakeup {
+    Exited(std::io::Result<std::process::ExitStatus>),
+    Health(HealthEvent),
+    WatchdogExpired,
+}
+
 /// Information about a managed process
 #[derive(Debug)]
 pub struct ProcessInfo {
     /// Current state of the process
     pub state: ProcessState,
-    /// Process ID (if running)
+    /// Process ID (if running), or the main PID reported with `MAINPID=`
     pub pid: Option<Pid>,
+    /// Process group of the current process, led by the PID scinit spawned
+    pub pgid: Option<Pid>,
+    /// Last status text reported with `STATUS=`
+    pub status: Option<String>,
     /// Start time of the current process
     pub start_time: std::time::Instant,
     /// Exit status of the last process (if stopped)
     pub exit_status: Option<std::process::ExitStatus>,
+    /// Whether the last process was killed by the OOM killer
+    pub oom_killed: bool,
 }
 
 /// Manages the lifecycle of child processes with support for graceful restarts
@@ -86,9 +198,37 @@ pub struct ProcessManager {
     /// Current process information
     process_info: ProcessInfo,
     /// Current child process handle
-    child: Option<Child>,
+    child: Option<OwnedChild>,
+    /// Previous generation still draining during an overlapping restart, with its cgroup
+    previous: Option<(Pid, OwnedChild, Option<Cgroup>)>,
     /// Whether the manager should stop managing processes
     should_stop: bool,
+    /// Restart budget and backoff tracking for crash restarts
+    restart_tracker: RestartTracker,
+    /// When a scheduled crash restart is due
+    pending_restart: Option<tokio::time::Instant>,
+    /// Consecutive failures to spawn after a file change, retried on `pending_restart`
+    spawn_failures: u32,
+    /// Sender handed to background probe tasks
+    health_tx: mpsc::UnboundedSender<HealthEvent>,
+    /// Events from background probe tasks
+    health_rx: mpsc::UnboundedReceiver<HealthEvent>,
+    /// Background probe tasks for the current process
+    health_tasks: Vec<JoinHandle<()>>,
+    /// sd_notify socket, bound on first spawn
+    notify_socket: Option<NotifySocket>,
+    /// Current watchdog interval, which the process may change with `WATCHDOG_USEC=`
+    watchdog_interval: Option<Duration>,
+    /// When the watchdog lapses unless the process pings it
+    watchdog_deadline: Option<tokio::time::Instant>,
+    /// Whether the last unhealthy event came from the watchdog rather than the liveness probe
+    watchdog_lapsed: bool,
+    /// The service's cgroup, created on first spawn
+    cgroup: Option<ServiceCgroup>,
+    /// Leaf cgroup of the current process
+    generation_cgroup: Option<Cgroup>,
+    /// cgroup whose OOM kill counter is watched, with its value at spawn
+    oom_baseline: Option<(PathBuf, u64)>,
 }
 
 impl ProcessManager {
@@ -101,17 +241,36 @@ impl ProcessManager {
     /// # Returns
     /// * `Self` - The process manager instance
     pub fn new(config: ProcessConfig, port_manager: PortManager) -> Self {
+        let (health_tx, health_rx) = mpsc::unbounded_channel();
+
         Self {
             process_info: ProcessInfo {
                 state: ProcessState::Stopped,
                 pid: None,
+                pgid: None,
+                status: None,
                 start_time: std::time::Instant::now(),
                 exit_status: None,
+                oom_killed: false,
             },
+            restart_tracker: RestartTracker::new(config.restart_policy.clone()),
             config,
             port_manager,
             child: None,
+            previous: None,
             should_stop: false,
+            pending_restart: None,
+            spawn_failures: 0,
+            health_tx,
+            health_rx,
+            health_tasks: Vec::new(),
+            notify_socket: None,
+            watchdog_interval: None,
+            watchdog_deadline: None,
+            watchdog_lapsed: false,
+            cgroup: None,
+            generation_cgroup: None,
+            oom_baseline: None,
         }
     }
 
@@ -133,15 +292,56 @@ impl ProcessManager {
         // Bind ports before spawning
         self.port_manager.bind_ports().await?;
 
-        // Prepare environment variables
-        let mut env_vars = std::env::vars().collect::<HashMap<_, _>>();
+        // Prepare environment variables; env files are re-read on every spawn
+        let mut env_vars = self.config.env.build(std::env::vars())?;
         
         // Add inherited file descriptors to environment
-        let inherited_fds = self.port_manager.get_inherited_fds_string();
+        // In socket activation mode the child sees them moved to fd 3 and up
+        let activation_fds = self.port_manager.socket_activation().then(|| {
+            (self.port_manager.get_inherited_fds(), self.port_manager.get_inherited_fd_names())
+        });
+        // Without socket activation the sockets keep their numbers and only lose close-on-exec
+        let plain_fds = match activation_fds {
+            Some(_) => Vec::new(),
+            None => self.port_manager.get_inherited_fds(),
+        };
+        let inherited_fds = match activation_fds {
+            Some((ref fds, _)) => SocketActivation::child_fds(fds.len())
+                .map(|fd| fd.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            None => self.port_manager.get_inherited_fds_string(),
+        };
         if !inherited_fds.is_empty() {
             env_vars.insert("SCINIT_INHERITED_FDS".to_string(), inherited_fds);
         }
 
+        // Offer the notify socket, bound once and kept across restarts
+        if let Some(ref notify) = self.config.notify {
+            if self.notify_socket.is_none() {
+                self.notify_socket = Some(NotifySocket::bind(notify.address.clone(), self.health_tx.clone())?);
+            }
+            if let Some(ref socket) = self.notify_socket {
+                env_vars.insert("NOTIFY_SOCKET".to_string(), socket.env_value());
+            }
+            // WATCHDOG_PID is left unset: the PID is only known after fork
+            env_vars.remove("WATCHDOG_PID");
+            match notify.watchdog {
+                Some(watchdog) => env_vars.insert("WATCHDOG_USEC".to_string(), watchdog.as_micros().to_string()),
+                None => env_vars.remove("WATCHDOG_USEC"),
+            };
+        }
+
+        // Describe the user the process runs as
+        if let Some(ref credentials) = self.config.credentials {
+            if let Some(ref name) = credentials.name {
+                env_vars.insert("USER".to_string(), name.clone());
+            }
+            if let Some(ref home) = credentials.home {
+                env_vars.insert("HOME".to_string(), home.display().to_string());
+            }
+        }
+
         // Add custom environment variables
         for (key, value) in &self.config.environment {
             env_vars.insert(key.clone(), value.clone());
@@ -160,11 +360,41 @@ impl ProcessManager {
         command.stdout(Stdio::inherit());
         command.stderr(Stdio::inherit());
 
+        // Socket activation installs the environment itself after fork, since
+        // LISTEN_PID has to carry the child's own PID
+        let mut activation = match activation_fds {
+            Some((fds, names)) => Some(SocketActivation::new(fds, names, env_vars.clone())?),
+            None => None,
+        };
+        let credentials = self.config.credentials.clone();
+
+        // Each process starts in a fresh leaf of the service's cgroup
+        let mut cgroup_procs = match self.config.cgroup {
+            Some(ref config) => {
+                let service = match self.cgroup {
+                    Some(ref mut service) => service,
+                    None => self.cgroup.insert(ServiceCgroup::create(config.clone())?),
+                };
+                let leaf = service.new_generation()?;
+                let procs = leaf.open_procs()?;
+                self.generation_cgroup = Some(leaf);
+                Some(procs)
+            }
+            None => None,
+        };
+        let rlimits = self.config.rlimits.clone();
+
+        // Only the service's own cgroup tells its OOM kills apart from other SIGKILLs;
+        // a shared cgroup would also count kills of unrelated processes
+        let oom_cgroup = self.cgroup.as_ref().map(|service| service.path().to_path_buf());
+        self.oom_baseline = oom_cgroup.and_then(|path| oom_kill_count(&path).map(|count| (path, count)));
+        self.process_info.oom_killed = false;
+
         // CRITICAL: Reset signal mask for child process
         // Child processes inherit the parent's signal mask, but we want them to handle signals normally
         // This is essential for terminal signals like Ctrl+C to work in child processes
         unsafe {
-            command.pre_exec(|| {
+            command.pre_exec(move || {
                 use nix::sys::signal::{pthread_sigmask, SigmaskHow, SigSet};
                 
                 // Create empty signal mask (unblock all signals)
@@ -173,6 +403,29 @@ impl ProcessManager {
                 // Reset signal mask to default state for child process
                 pthread_sigmask(SigmaskHow::SIG_SETMASK, Some(&empty_mask), None)
                     .map_err(|e| std::io::Error::from_raw_os_error(e as i32))?;
+
+                // Join the cgroup before anything else runs, so nothing escapes it
+                if let Some(ref mut procs) = cgroup_procs {
+                    procs.write_all(b"0")?;
+                }
+
+                // Move listening sockets to fd 3+ and set LISTEN_PID
+                if let Some(ref mut activation) = activation {
+                    activation.apply_in_child()?;
+                }
+                for &fd in &plain_fds {
+                    inherit_in_child(fd)?;
+                }
+
+                // Raising hard limits needs privileges, so this comes before dropping them
+                for rlimit in &rlimits {
+                    rlimit.apply()?;
+                }
+
+                // Drop privileges last, once the listening sockets are in place
+                if let Some(ref credentials) = credentials {
+                    credentials.apply()?;
+                }
                 
                 Ok(())
             });
@@ -183,14 +436,16 @@ impl ProcessManager {
             command.current_dir(work_dir);
         }
 
-        // Set environment variables
-        command.env_clear();
-        for (key, value) in env_vars {
-            command.env(key, value);
+        // Set environment variables, unless socket activation installs them after fork
+        if !self.port_manager.socket_activation() {
+            command.env_clear();
+            for (key, value) in env_vars {
+                command.env(key, value);
+            }
         }
 
         // Spawn the process
-        let child = command.spawn()
+        let child = spawn_owned(&mut command)
             .map_err(|e| eyre!("Failed to spawn process '{}': {}", self.config.command, e))?;
         
         // Get the PID
@@ -201,14 +456,217 @@ impl ProcessManager {
 
         // Update process info
         self.process_info.pid = Some(pid);
+        self.process_info.pgid = Some(pid);
+        self.process_info.status = None;
         self.process_info.state = ProcessState::Running;
         self.process_info.start_time = std::time::Instant::now();
         self.child = Some(child);
+        self.start_health_tasks(pid);
 
         info!("Process spawned with PID: {}", pid);
         Ok(())
     }
 
+    /// Starts the background probes and the watchdog for the process with the given PID
+    ///
+    /// Probes of a previous process are cancelled first.
+    fn start_health_tasks(&mut self, pid: Pid) {
+        self.stop_health_tasks();
+
+        if let Some(readiness) = self.config.readiness.as_ref().filter(|r| r.probe.is_some()) {
+            debug!("Starting readiness probe for process {}", pid);
+            self.health_tasks
+                .push(spawn_readiness_probe(readiness.clone(), pid, self.health_tx.clone()));
+        }
+        if let Some(ref liveness) = self.config.liveness {
+            debug!("Starting liveness probe {} for process {}", liveness.probe, pid);
+            self.health_tasks
+                .push(spawn_liveness_probe(liveness.clone(), pid, self.health_tx.clone()));
+        }
+        self.watchdog_interval = self.config.notify.as_ref().and_then(|notify| notify.watchdog);
+        self.watchdog_deadline = self.watchdog_interval.map(|interval| tokio::time::Instant::now() + interval);
+        self.watchdog_lapsed = false;
+    }
+
+    /// Cancels the background probes and the watchdog of the current process
+    fn stop_health_tasks(&mut self) {
+        for task in self.health_tasks.drain(..) {
+            task.abort();
+        }
+        self.watchdog_interval = None;
+        self.watchdog_deadline = None;
+    }
+
+    /// Waits for the next event of the current process
+    ///
+    /// Resolves when the process exits, becomes ready, or fails its liveness
+    /// probe or watchdog. Status notifications are handled along the way.
+    /// Events belonging to an earlier process are discarded. This method is
+    /// cancel-safe.
+    ///
+    /// # Returns
+    /// * `Result<ProcessEvent>` - The event, or an error if there is no process
+    pub async fn next_event(&mut self) -> Result<ProcessEvent> {
+        loop {
+            let wakeup = {
+                let Some(child) = self.child.as_mut() else {
+                    return Err(eyre!("No process to wait for"));
+                };
+                let health_rx = &mut self.health_rx;
+                let watchdog = self.watchdog_deadline;
+                select! {
+                    result = child.wait() => Wakeup::Exited(result),
+                    Some(event) = health_rx.recv() => Wakeup::Health(event),
+                    _ = tokio::time::sleep_until(watchdog.unwrap_or_else(tokio::time::Instant::now)), if watchdog.is_some() => {
+                        Wakeup::WatchdogExpired
+                    }
+                }
+            };
+
+            let event = match wakeup {
+                Wakeup::Exited(result) => return self.record_exit(result).map(ProcessEvent::Exited),
+                Wakeup::Health(HealthEvent::Ready(pid)) => {
+                    if self.process_info.pgid != Some(pid) {
+                        debug!("Ignoring stale readiness event for process {}", pid);
+                        continue;
+                    }
+                    self.mark_ready()
+                }
+                Wakeup::Health(HealthEvent::Unhealthy(pid)) => {
+                    if self.process_info.pgid != Some(pid) || !self.is_running() {
+                        debug!("Ignoring stale liveness event for process {}", pid);
+                        continue;
+                    }
+                    Some(ProcessEvent::Unhealthy(pid))
+                }
+                Wakeup::Health(HealthEvent::Notify(sender, notification)) => {
+                    self.handle_notification(sender, notification)
+                }
+                Wakeup::WatchdogExpired => {
+                    self.watchdog_deadline = None;
+                    error!("Watchdog timeout after {:?} without WATCHDOG=1", self.watchdog_interval.unwrap_or_default());
+                    self.watchdog_lapsed = true;
+                    self.process_info.pgid.map(ProcessEvent::Unhealthy)
+                }
+            };
+            if let Some(event) = event {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Moves a running process to the `Ready` state
+    ///
+    /// # Returns
+    /// * `Option<ProcessEvent>` - The `Ready` event, or None if the process was already ready
+    fn mark_ready(&mut self) -> Option<ProcessEvent> {
+        if self.process_info.state != ProcessState::Running {
+            return None;
+        }
+        let pid = self.process_info.pid?;
+        self.process_info.state = ProcessState::Ready;
+        info!("Process {} is ready after {:?}", pid, self.process_info.start_time.elapsed());
+        Some(ProcessEvent::Ready(pid))
+    }
+
+    /// Applies a message received on the notify socket
+    ///
+    /// Messages are accepted from any process in the current process group.
+    ///
+    /// # Arguments
+    /// * `sender` - PID of the process that sent the message
+    /// * `notification` - The parsed notification
+    ///
+    /// # Returns
+    /// * `Option<ProcessEvent>` - An event for the caller, if the notification produced one
+    fn handle_notification(&mut self, sender: Pid, notification: Notification) -> Option<ProcessEvent> {
+        let pgid = self.process_info.pgid?;
+        let in_group = sender == pgid
+            || Some(sender) == self.process_info.pid
+            || nix::unistd::getpgid(Some(sender)).ok() == Some(pgid);
+        if !in_group {
+            debug!("Ignoring notification {:?} from process {} outside process group {}", notification, sender, pgid);
+            return None;
+        }
+
+        match notification {
+            Notification::Ready => return self.mark_ready(),
+            Notification::Status(status) => {
+                info!("Process {} status: {}", sender, status);
+                self.process_info.status = Some(status);
+            }
+            Notification::MainPid(pid) => {
+                // Like systemd, only a process of the service may become its main process
+                let in_group = nix::unistd::getpgid(Some(pid)).ok() == Some(pgid);
+                let in_cgroup = self.generation_cgroup.as_ref().is_some_and(|cgroup| cgroup.pids().contains(&pid));
+                if !in_group && !in_cgroup {
+                    warn!("Ignoring MAINPID={} from process {}: not a process of this service", pid, sender);
+                    return None;
+                }
+                info!("Process {} reported main PID {}", sender, pid);
+                self.process_info.pid = Some(pid);
+            }
+            Notification::Watchdog => {
+                if let Some(interval) = self.watchdog_interval {
+                    self.watchdog_deadline = Some(tokio::time::Instant::now() + interval);
+                }
+            }
+            Notification::WatchdogUsec(interval) => {
+                info!("Process {} set watchdog interval to {:?}", sender, interval);
+                self.watchdog_interval = Some(interval);
+                self.watchdog_deadline = Some(tokio::time::Instant::now() + interval);
+            }
+            Notification::WatchdogTrigger => {
+                error!("Process {} triggered its watchdog", sender);
+                self.watchdog_deadline = None;
+                self.watchdog_lapsed = true;
+                return Some(ProcessEvent::Unhealthy(pgid));
+            }
+            Notification::Stopping => {
+                info!("Process {} is stopping", sender);
+                self.watchdog_deadline = None;
+            }
+        }
+        None
+    }
+
+    /// Records the outcome of waiting on the current child
+    fn record_exit(&mut self, result: std::io::Result<std::process::ExitStatus>) -> Result<std::process::ExitStatus> {
+        self.stop_health_tasks();
+        self.child = None;
+
+        // Checked before killing leftovers, which are not OOM kills
+        let oom_baseline = self.oom_baseline.take();
+        if let Ok(ref status) = result {
+            use std::os::unix::process::ExitStatusExt;
+            self.process_info.oom_killed = status.signal() == Some(libc::SIGKILL)
+                && oom_baseline.is_some_and(|(path, before)| oom_kill_count(&path).is_some_and(|now| now > before));
+            if self.process_info.oom_killed {
+                error!("Process was killed by the OOM killer");
+            }
+        }
+
+        // Descendants that left the process group go with the process
+        if let Some(cgroup) = self.generation_cgroup.take() {
+            kill_leftovers(&cgroup);
+        }
+
+        match result {
+            Ok(status) => {
+                self.process_info.exit_status = Some(status);
+                self.process_info.state = ProcessState::Stopped;
+
+                debug!("Process exited with status: {:?}", status);
+                Ok(status)
+            }
+            Err(e) => {
+                error!("Error waiting for process: {}", e);
+                self.process_info.state = ProcessState::Failed;
+                Err(e.into())
+            }
+        }
+    }
+
     /// Waits for the current process to exit
     /// 
     /// This method waits for the child process to exit and returns
@@ -218,22 +676,8 @@ impl ProcessManager {
     /// * `Result<Option<std::process::ExitStatus>>` - Exit status or None if no process
     pub async fn wait_for_exit(&mut self) -> Result<Option<std::process::ExitStatus>> {
         if let Some(ref mut child) = self.child {
-            match child.wait().await {
-                Ok(status) => {
-                    self.process_info.exit_status = Some(status);
-                    self.process_info.state = ProcessState::Stopped;
-                    self.child = None;
-                    
-                    debug!("Process exited with status: {:?}", status);
-                    Ok(Some(status))
-                }
-                Err(e) => {
-                    error!("Error waiting for process: {}", e);
-                    self.process_info.state = ProcessState::Failed;
-                    self.child = None;
-                    Err(e.into())
-                }
-            }
+            let result = child.wait().await;
+            self.record_exit(result).map(Some)
         } else {
             Ok(None)
         }
@@ -241,20 +685,23 @@ impl ProcessManager {
 
     /// Performs a graceful shutdown of the current process
     /// 
-    /// This method sends SIGTERM to the process and waits for it to exit
-    /// gracefully. If the process doesn't exit within the timeout,
+    /// This method sends the stop signal to the process and waits for it to
+    /// exit gracefully. If the process doesn't exit within the timeout,
     /// it sends SIGKILL.
     /// 
     /// # Returns
     /// * `Result<()>` - Success or error
     pub async fn graceful_shutdown(&mut self) -> Result<()> {
+        if self.child.is_none() {
+            return Ok(());
+        }
+
         if let Some(pid) = self.process_info.pid {
             self.process_info.state = ProcessState::Stopping;
             info!("Initiating graceful shutdown of process {}", pid);
 
-            // Send SIGTERM
-            if let Err(e) = self.forward_signal(Signal::SIGTERM) {
-                warn!("Failed to send SIGTERM: {}", e);
+            if let Err(e) = self.send_stop_signal() {
+                warn!("Failed to send {:?}: {}", self.config.stop_signal, e);
             }
 
             // Wait for graceful shutdown
@@ -289,8 +736,12 @@ impl ProcessManager {
         if let Some(pid) = self.process_info.pid {
             info!("Force killing process {}", pid);
 
-            // Send SIGKILL
-            if let Err(e) = self.forward_signal(Signal::SIGKILL) {
+            // Send SIGKILL, to the whole cgroup if the process has one
+            let killed = match self.generation_cgroup {
+                Some(ref cgroup) => cgroup.kill(),
+                None => self.forward_signal(Signal::SIGKILL),
+            };
+            if let Err(e) = killed {
                 warn!("Failed to send SIGKILL: {}", e);
             }
 
@@ -298,13 +749,10 @@ impl ProcessManager {
             sleep(Duration::from_millis(100)).await;
             
             // Check if process is still running
-            if let Some(ref mut child) = self.child {
-                if let Ok(Some(status)) = child.try_wait() {
-                    self.process_info.exit_status = Some(status);
-                    self.process_info.state = ProcessState::Stopped;
-                    self.child = None;
-                    info!("Process killed, exit status: {:?}", status);
-                }
+            let exited = self.child.as_mut().and_then(|child| child.try_wait().ok().flatten());
+            if let Some(status) = exited {
+                self.record_exit(Ok(status))?;
+                info!("Process killed, exit status: {:?}", status);
             }
         }
 
@@ -315,38 +763,338 @@ impl ProcessManager {
     /// Restarts the current process with a specific reason
     /// 
     /// This method performs a graceful shutdown of the current process and
-    /// spawns a new one. Only file-change restarts are allowed in container environments.
+    /// spawns a new one. File-change restarts and restarts requested with a
+    /// signal are always allowed; crash and OOM-kill restarts only when the
+    /// restart policy permits them.
     /// 
     /// # Arguments
     /// * `reason` - The reason for the restart (for logging and limit checking)
     /// 
     /// # Returns
-    /// * `Result<bool>` - True if restart was successful, false if restart not allowed
-    pub async fn restart_process_with_reason(&mut self, reason: &str) -> Result<bool> {
+    /// * `Result<RestartOutcome>` - Whether the process was restarted, the restart
+    ///   wasn't allowed, or the running process was kept
+    pub async fn restart_process_with_reason(&mut self, reason: RestartReason) -> Result<RestartOutcome> {
+        self.restart_process_with_mode(reason, self.config.restart_mode).await
+    }
+
+    /// Restarts the current process with a specific reason, replacing it as `mode` says
+    ///
+    /// # Arguments
+    /// * `reason` - The reason for the restart (for logging and limit checking)
+    /// * `mode` - Whether to stop the old process first or overlap the two generations
+    ///
+    /// # Returns
+    /// * `Result<RestartOutcome>` - As for [`Self::restart_process_with_reason`]
+    pub async fn restart_process_with_mode(&mut self, reason: RestartReason, mode: RestartMode) -> Result<RestartOutcome> {
         if self.should_stop {
-            return Ok(false);
+            return Ok(RestartOutcome::NotAllowed);
         }
 
-        // Only allow file-change restarts, not crash restarts
-        let is_file_change_restart = reason == "file_change";
-        
-        if !is_file_change_restart {
-            error!("Process restart not allowed for reason: {} (only file-change restarts are allowed)", reason);
-            return Ok(false);
+        match reason {
+            RestartReason::FileChange => info!("Restarting process due to file change"),
+            RestartReason::Signal(signal) => info!("Restarting process on {} from the operator", signal.as_str()),
+            RestartReason::SpawnRetry => info!("Retrying start of the changed process (attempt {})", self.spawn_failures + 1),
+            RestartReason::Crash | RestartReason::Oom
+                if self.pending_restart.is_some() || self.restart_tracker.policy() != RestartPolicy::Never =>
+            {
+                info!(
+                    "Restarting process after {} (restart {} in current window)",
+                    if reason == RestartReason::Oom { "OOM kill" } else { "exit" },
+                    self.restart_tracker.restarts_in_window()
+                );
+            }
+            RestartReason::Crash | RestartReason::Oom => {
+                error!("Process restart not allowed for reason: {:?} (restart policy: {:?})", reason, self.restart_tracker.policy());
+                return Ok(RestartOutcome::NotAllowed);
+            }
+        }
+
+        self.pending_restart = None;
+
+        // A broken env file would otherwise only surface once the old process is gone
+        if self.child.is_some() {
+            if let Err(e) = self.config.env.build(std::env::vars()) {
+                error!("Keeping the running process: {}", e);
+                return Ok(RestartOutcome::Kept);
+            }
+        }
+
+        if self.child.is_some() && mode == RestartMode::Overlap {
+            return self.overlapping_restart().await;
+        }
+
+        // Graceful shutdown current process, waiting the restart delay if one was running
+        if self.child.is_some() {
+            self.graceful_shutdown().await?;
+            sleep(self.config.restart_delay).await;
+        }
+
+        // Spawn new process; a file change may have left a binary that can't
+        // run yet, so that case is retried against the restart budget rather
+        // than treated as fatal
+        if let Err(e) = self.spawn_process().await {
+            if !matches!(reason, RestartReason::FileChange | RestartReason::SpawnRetry) {
+                return Err(e);
+            }
+            self.spawn_failures += 1;
+            match self.restart_tracker.charge(std::time::Instant::now()) {
+                RestartDecision::Restart(delay) => {
+                    let delay = delay.max(MIN_SPAWN_RETRY_DELAY);
+                    error!("Failed to start the changed process: {}; retrying in {:?}", e, delay);
+                    self.pending_restart = Some(tokio::time::Instant::now() + delay);
+                    return Ok(RestartOutcome::Restarted);
+                }
+                _ => {
+                    error!(
+                        "Failed to start the changed process: {}; restart budget exhausted ({} restarts in window)",
+                        e,
+                        self.restart_tracker.restarts_in_window()
+                    );
+                    self.process_info.state = ProcessState::Failed;
+                    return Ok(RestartOutcome::Exhausted);
+                }
+            }
+        }
+        self.spawn_failures = 0;
+
+        Ok(RestartOutcome::Restarted)
+    }
+
+    /// Replaces the running process without leaving the listeners unserviced
+    ///
+    /// The new generation is spawned on the same inherited sockets while the
+    /// old one keeps serving. Only once the new process is ready does the old
+    /// process group get SIGTERM. If the new process fails to start or exits
+    /// before becoming ready, the old generation is kept.
+    ///
+    /// # Returns
+    /// * `Result<RestartOutcome>` - `Restarted`, or `Kept` if the old generation stays
+    async fn overlapping_restart(&mut self) -> Result<RestartOutcome> {
+        let old_pid = match (self.process_info.pgid, self.child.take()) {
+            (Some(pid), Some(child)) => {
+                self.previous = Some((pid, child, self.generation_cgroup.take()));
+                pid
+            }
+            _ => return Err(eyre!("No running process to replace")),
+        };
+        info!("Starting new generation alongside process {}", old_pid);
+
+        // Spawning resets OOM tracking for the new generation
+        let oom_baseline = self.oom_baseline.clone();
+        let ready = match self.spawn_process().await {
+            Ok(()) => self.wait_until_ready().await?,
+            Err(e) => {
+                error!("Failed to start new generation: {}", e);
+                false
+            }
+        };
+
+        if !ready {
+            warn!("New generation did not become ready, keeping process {}", old_pid);
+            self.graceful_shutdown().await?;
+            if let Some((pid, child, cgroup)) = self.previous.take() {
+                self.process_info.pid = Some(pid);
+                self.process_info.pgid = Some(pid);
+                self.process_info.state = ProcessState::Running;
+                self.child = Some(child);
+                self.generation_cgroup = cgroup;
+                self.oom_baseline = oom_baseline;
+                self.process_info.oom_killed = false;
+                self.start_health_tasks(pid);
+            }
+            return Ok(RestartOutcome::Kept);
+        }
+
+        self.retire_previous().await;
+        Ok(RestartOutcome::Restarted)
+    }
+
+    /// Waits for a freshly spawned process to be considered ready
+    ///
+    /// With a readiness probe the process must pass it within the startup
+    /// timeout. Without one, it counts as ready once it has stayed up for the
+    /// restart delay.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - True if ready, false if the process exited or timed out first
+    async fn wait_until_ready(&mut self) -> Result<bool> {
+        if let Some(startup_timeout) = self.config.readiness.as_ref().map(|r| r.startup_timeout) {
+            let wait = async {
+                match self.next_event().await? {
+                    ProcessEvent::Ready(_) => Ok(true),
+                    ProcessEvent::Exited(status) => {
+                        warn!("New process exited during startup with {}", status);
+                        Ok(false)
+                    }
+                    ProcessEvent::Unhealthy(pid) => {
+                        warn!("New process {} failed its liveness probe during startup", pid);
+                        Ok(false)
+                    }
+                }
+            };
+            return match timeout(startup_timeout, wait).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!("New process did not become ready within {:?}", startup_timeout);
+                    Ok(false)
+                }
+            };
+        }
+
+        match timeout(self.config.restart_delay, self.wait_for_exit()).await {
+            Err(_) => Ok(true),
+            Ok(Ok(Some(status))) => {
+                warn!("New process exited during startup with {}", status);
+                Ok(false)
+            }
+            Ok(Ok(None)) => Ok(false),
+            Ok(Err(e)) => Err(e),
+        }
+    }
+
+    /// Gracefully stops the previous generation after an overlapping restart
+    async fn retire_previous(&mut self) {
+        let Some((pid, mut child, cgroup)) = self.previous.take() else {
+            return;
+        };
+        info!("Stopping previous generation {}", pid);
+
+        let stop_signal = self.config.stop_signal;
+        let sent = match self.config.stop_target {
+            SignalTarget::Group => signal_process_group(pid, stop_signal),
+            SignalTarget::Child => nix::sys::signal::kill(pid, stop_signal).map_err(Into::into),
+        };
+        if let Err(e) = sent {
+            warn!("Failed to send {:?} to previous generation {}: {}", stop_signal, pid, e);
+        }
+
+        match timeout(self.config.graceful_shutdown_timeout, child.wait()).await {
+            Ok(Ok(status)) => info!("Previous generation {} exited with {}", pid, status),
+            Ok(Err(e)) => warn!("Error waiting for previous generation {}: {}", pid, e),
+            Err(_) => {
+                warn!("Previous generation {} did not exit in time, forcing kill", pid);
+                let killed = match cgroup {
+                    Some(ref cgroup) => cgroup.kill(),
+                    None => signal_process_group(pid, Signal::SIGKILL),
+                };
+                if let Err(e) = killed {
+                    warn!("Failed to send SIGKILL to previous generation {}: {}", pid, e);
+                }
+                let _ = child.wait().await;
+            }
+        }
+
+        if let Some(ref cgroup) = cgroup {
+            kill_leftovers(cgroup);
+        }
+    }
+
+    /// Consults the restart policy after the process exited on its own
+    ///
+    /// A `Restart` decision schedules the restart; the caller should invoke
+    /// `restart_process_with_reason` with `pending_restart_reason()` once
+    /// `pending_restart` is due.
+    /// An `Exhausted` decision moves the manager to the `Failed` state.
+    ///
+    /// # Arguments
+    /// * `status` - Exit status of the process
+    ///
+    /// # Returns
+    /// * `RestartDecision` - What the caller should do next
+    pub fn schedule_restart(&mut self, status: std::process::ExitStatus) -> RestartDecision {
+        if self.should_stop {
+            return RestartDecision::Exit;
+        }
+
+        let decision = self.restart_tracker.decide(status, std::time::Instant::now());
+        let what = if self.process_info.oom_killed {
+            "Process was killed by the OOM killer".to_string()
+        } else {
+            format!("Process exited with {}", status)
+        };
+        self.apply_restart_decision(decision, &what)
+    }
+
+    /// Schedules a restart after the process exited, whatever the restart policy says
+    ///
+    /// Used when the caller wants the process back regardless of how it
+    /// exited. The restart still counts against the budget and backoff.
+    ///
+    /// # Returns
+    /// * `RestartDecision` - `Restart` or `Exhausted`
+    pub fn schedule_forced_restart(&mut self) -> RestartDecision {
+        if self.should_stop {
+            return RestartDecision::Exit;
         }
 
-        info!("Restarting process due to file change");
+        let decision = self.restart_tracker.charge(std::time::Instant::now());
+        self.apply_restart_decision(decision, "Process exited")
+    }
+
+    /// Schedules the restart or marks the process failed, as decided
+    fn apply_restart_decision(&mut self, decision: RestartDecision, what: &str) -> RestartDecision {
+        match decision {
+            RestartDecision::Restart(delay) => {
+                info!("{}, restarting in {:?} ({:?} policy)", what, delay, self.restart_tracker.policy());
+                self.pending_restart = Some(tokio::time::Instant::now() + delay);
+            }
+            RestartDecision::Exhausted => {
+                error!(
+                    "{}, restart budget exhausted ({} restarts in window)",
+                    what,
+                    self.restart_tracker.restarts_in_window()
+                );
+                self.process_info.state = ProcessState::Failed;
+            }
+            RestartDecision::Exit => {}
+        }
+        decision
+    }
 
-        // Graceful shutdown current process
+    /// Stops a process that failed its liveness probe or watchdog and consults the restart policy
+    ///
+    /// The process goes through the normal graceful shutdown, escalating to
+    /// SIGKILL after the timeout. The stop counts as a failure against the
+    /// restart budget regardless of the exit status it produced. A lapsed
+    /// watchdog restarts the process whatever the restart policy; only the
+    /// budget applies.
+    ///
+    /// # Returns
+    /// * `Result<RestartDecision>` - What the caller should do next
+    pub async fn handle_unhealthy(&mut self) -> Result<RestartDecision> {
+        warn!("Process is unhealthy, stopping it");
         self.graceful_shutdown().await?;
 
-        // Wait for restart delay
-        sleep(self.config.restart_delay).await;
+        if self.should_stop {
+            return Ok(RestartDecision::Exit);
+        }
+
+        let now = std::time::Instant::now();
+        let decision = if std::mem::take(&mut self.watchdog_lapsed) {
+            self.restart_tracker.charge(now)
+        } else {
+            self.restart_tracker.decide_unhealthy(now)
+        };
+        Ok(self.apply_restart_decision(decision, "Process unhealthy"))
+    }
 
-        // Spawn new process
-        self.spawn_process().await?;
+    /// Reason to pass to `restart_process_with_reason` once the pending restart is due
+    pub fn pending_restart_reason(&self) -> RestartReason {
+        if self.spawn_failures > 0 {
+            RestartReason::SpawnRetry
+        } else if self.process_info.oom_killed {
+            RestartReason::Oom
+        } else {
+            RestartReason::Crash
+        }
+    }
 
-        Ok(true)
+    /// Gets the time at which a scheduled crash restart is due
+    /// 
+    /// # Returns
+    /// * `Option<tokio::time::Instant>` - Deadline of the pending restart, if any
+    pub fn pending_restart(&self) -> Option<tokio::time::Instant> {
+        self.pending_restart
     }
 
     /// Forwards a signal to the current process
@@ -360,6 +1108,66 @@ impl ProcessManager {
         self.send_signal_to_group(signal)
     }
 
+    /// Sends a signal to the process group or to the main process only
+    ///
+    /// # Arguments
+    /// * `signal` - The signal to send
+    /// * `target` - Whether the whole process group receives it
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error
+    pub fn send_signal(&self, signal: Signal, target: SignalTarget) -> Result<()> {
+        match target {
+            SignalTarget::Group => self.send_signal_to_group(signal),
+            SignalTarget::Child => match self.process_info.pid {
+                Some(pid) => {
+                    debug!("Sending signal {:?} to process {}", signal, pid);
+                    nix::sys::signal::kill(pid, signal)?;
+                    Ok(())
+                }
+                None => Err(eyre!("No process to send signal to")),
+            },
+        }
+    }
+
+    /// Sends a signal given by number, such as a real-time signal, to the process group or main process only
+    ///
+    /// # Arguments
+    /// * `signal` - The signal number
+    /// * `target` - Whether the whole process group receives it
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error
+    pub fn send_raw_signal(&self, signal: i32, target: SignalTarget) -> Result<()> {
+        if let Ok(signal) = Signal::try_from(signal) {
+            return self.send_signal(signal, target);
+        }
+        let pid = match target {
+            SignalTarget::Group => self.process_info.pgid.map(|pgid| -pgid.as_raw()),
+            SignalTarget::Child => self.process_info.pid.map(Pid::as_raw),
+        };
+        let pid = pid.ok_or_else(|| eyre!("No process to send signal to"))?;
+        debug!("Sending signal {} to {}", signal_name(signal), pid);
+        // SAFETY: kill has no memory safety requirements
+        if unsafe { libc::kill(pid, signal) } == -1 {
+            return Err(nix::errno::Errno::last().into());
+        }
+        Ok(())
+    }
+
+    /// Gets the signal used to stop the process gracefully
+    pub fn stop_signal(&self) -> Signal {
+        self.config.stop_signal
+    }
+
+    /// Sends the stop signal to its configured target
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error
+    pub fn send_stop_signal(&self) -> Result<()> {
+        self.send_signal(self.config.stop_signal, self.config.stop_target)
+    }
+
     /// Sends a signal to the process group (synchronous version for Drop)
     /// 
     /// # Arguments
@@ -368,14 +1176,8 @@ impl ProcessManager {
     /// # Returns
     /// * `Result<()>` - Success or error
     pub fn send_signal_to_group(&self, signal: Signal) -> Result<()> {
-        if let Some(pid) = self.process_info.pid {
-            use nix::sys::signal::kill;
-            let pgid = getpgid(Some(pid))?;
-            debug!("Sending signal {:?} to process group {}", signal, pgid);
-            
-            // Send signal to the entire process group
-            kill(Pid::from_raw(-pgid.as_raw()), signal)?;
-            Ok(())
+        if let Some(pgid) = self.process_info.pgid {
+            signal_process_group(pgid, signal)
         } else {
             Err(eyre!("No process to send signal to"))
         }
@@ -398,12 +1200,20 @@ impl ProcessManager {
         self.process_info.state.clone()
     }
 
+    /// Checks if there is a child process to wait for
+    /// 
+    /// # Returns
+    /// * `bool` - True if a child handle is held
+    pub fn has_child(&self) -> bool {
+        self.child.is_some()
+    }
+
     /// Checks if the process is running
     /// 
     /// # Returns
     /// * `bool` - True if the process is running
     pub fn is_running(&self) -> bool {
-        self.process_info.state == ProcessState::Running
+        matches!(self.process_info.state, ProcessState::Running | ProcessState::Ready)
     }
 
     /// Stops the process manager
@@ -413,6 +1223,7 @@ impl ProcessManager {
     #[allow(dead_code)]
     pub fn stop(&mut self) {
         self.should_stop = true;
+        self.pending_restart = None;
         info!("Process manager stopped");
     }
 
@@ -424,8 +1235,7 @@ impl Drop for ProcessManager {
         // Only attempt cleanup if we still have a running process
         if let Some(pid) = self.process_info.pid {
             // Check if process is actually still running before emergency cleanup
-            if self.process_info.state == ProcessState::Running || 
-               self.process_info.state == ProcessState::Starting {
+            if self.is_running() || self.process_info.state == ProcessState::Starting {
                 eprintln!("ProcessManager dropped with running child (PID: {}), emergency cleanup", pid);
                 
                 // Emergency SIGKILL to process group - no graceful shutdown in Drop
@@ -448,6 +1258,26 @@ impl Drop for ProcessManager {
             }
         }
         
+        self.stop_health_tasks();
+
+        // A previous generation left over from an interrupted overlapping restart
+        if let Some((pid, _, cgroup)) = self.previous.take() {
+            if let Err(e) = signal_process_group(pid, Signal::SIGKILL) {
+                eprintln!("Failed to send SIGKILL to previous generation {} during emergency cleanup: {}", pid, e);
+            }
+            if let Some(cgroup) = cgroup {
+                let _ = cgroup.kill();
+            }
+        }
+
+        // Processes that escaped the process group
+        if let Some(cgroup) = self.generation_cgroup.take() {
+            let _ = cgroup.kill();
+        }
+        if let Some(service) = self.cgroup.take() {
+            service.remove();
+        }
+
         // Stop the process manager to prevent further operations
         self.should_stop = true;
         
@@ -456,25 +1286,334 @@ impl Drop for ProcessManager {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::port_manager::PortBindingConfig;
-
-    #[tokio::test]
-    async fn test_process_manager_creation() {
-        let config = ProcessConfig::default();
-        let port_config = PortBindingConfig::default();
-        let port_manager = PortManager::new(port_config);
-        
-        let manager = ProcessManager::new(config, port_manager);
-        assert_eq!(manager.state(), ProcessState::Stopped);
-        assert!(!manager.is_running());
+/// Kills whatever is left in a generation's cgroup once its main process is gone
+fn kill_leftovers(cgroup: &Cgroup) {
+    if !cgroup.is_populated() {
+        return;
+    }
+    warn!("Killing processes left in cgroup {}: {:?}", cgroup.path().display(), cgroup.pids());
+    if let Err(e) = cgroup.kill() {
+        warn!("Failed to kill processes in cgroup {}: {}", cgroup.path().display(), e);
     }
+}
 
-    #[tokio::test]
-    async fn test_process_spawn() {
-        let config = ProcessConfig {
+/// Sends a signal to the process group led by `pid`
+fn signal_process_group(pid: Pid, signal: Signal) -> Result<()> {
+    use nix::sys::signal::kill;
+    let pgid = getpgid(Some(pid))?;
+    debug!("Sending signal {:?} to process group {}", signal, pgid);
+
+    // Send signal to the entire process group
+    kill(Pid::from_raw(-pgid.as_raw()), signal)?;
+    Ok(())
+}
+
+/// Sets the process group as the foreground process group if a terminal is available
+pub fn process_group_to_foreground(pgid: Pid) -> Result<()> {
+    match File::open("/dev/tty") {
+        Ok(tty) => {
+            if tty.is_terminal() {
+                debug!("Setting process group {} as foreground", &pgid);
+                if let Err(e) = tcsetpgrp(tty, pgid) {
+                    error!("Failed to set process group {} as foreground: {}", &pgid, e);
+                    return Err(e.into());
+                }
+            } else {
+                debug!("Not a terminal, skipping foreground process group setup");
+            }
+        }
+        Err(e) => {
+            debug!(
+                "Cannot open /dev/tty ({}), skipping foreground process group setup",
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// How orphaned descendants end up being reaped by scinit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReapingMode {
+    /// scinit is PID 1 and inherits every orphan in its PID namespace
+    Pid1,
+    /// scinit is registered as a child subreaper for its descendants
+    Subreaper,
+    /// scinit only reaps its direct children; orphans go to another reaper
+    DirectChildrenOnly,
+}
+
+impl std::fmt::Display for ReapingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReapingMode::Pid1 => write!(f, "PID 1"),
+            ReapingMode::Subreaper => write!(f, "child subreaper"),
+            ReapingMode::DirectChildrenOnly => write!(f, "direct children only"),
+        }
+    }
+}
+
+/// Sets up zombie reaping for descendants of the managed processes
+///
+/// When scinit is not PID 1 (e.g. under a shell or `docker run --init`),
+/// orphaned grandchildren would be re-parented to some other process. Registering
+/// as a child subreaper with `PR_SET_CHILD_SUBREAPER` makes them scinit's
+/// children instead, so `reap_zombies` collects them regardless of scinit's PID.
+///
+/// # Arguments
+/// * `subreaper` - Whether to register as a child subreaper when not PID 1
+///
+/// # Returns
+/// * `Result<ReapingMode>` - The active reaping mode
+pub fn setup_reaping(subreaper: bool) -> Result<ReapingMode> {
+    let mode = if nix::unistd::getpid() == Pid::from_raw(1) {
+        ReapingMode::Pid1
+    } else if subreaper {
+        nix::sys::prctl::set_child_subreaper(true)
+            .map_err(|e| eyre!("Failed to register as child subreaper: {}", e))?;
+        ReapingMode::Subreaper
+    } else {
+        ReapingMode::DirectChildrenOnly
+    };
+
+    info!("Zombie reaping mode: {}", mode);
+    if mode == ReapingMode::DirectChildrenOnly {
+        warn!("scinit is not PID 1 and not a subreaper; orphaned descendants will not be reaped by scinit");
+    }
+    Ok(mode)
+}
+
+/// Children whose exit status belongs to a tokio `Child`: managed processes and helpers such as exec probes
+static OWNED_PIDS: Mutex<Vec<Pid>> = Mutex::new(Vec::new());
+
+fn owned_pids() -> MutexGuard<'static, Vec<Pid>> {
+    OWNED_PIDS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A child registered with the zombie reaper, unregistered when dropped
+///
+/// Dereferences to the `Child`, which the owner waits on as usual.
+#[derive(Debug)]
+pub struct OwnedChild {
+    child: Child,
+    pid: Option<Pid>,
+}
+
+impl std::ops::Deref for OwnedChild {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        &self.child
+    }
+}
+
+impl std::ops::DerefMut for OwnedChild {
+    fn deref_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+}
+
+impl Drop for OwnedChild {
+    fn drop(&mut self) {
+        if let Some(pid) = self.pid {
+            owned_pids().retain(|owned| *owned != pid);
+        }
+    }
+}
+
+/// Spawns a child whose exit status the zombie reaper leaves to the returned `OwnedChild`
+///
+/// The registry stays locked while spawning, so the reaper can't take the
+/// child's status before its pid is registered. Keep the `OwnedChild` until
+/// the child has been waited for.
+///
+/// # Arguments
+/// * `command` - The command to spawn
+///
+/// # Returns
+/// * `std::io::Result<OwnedChild>` - The registered child
+pub fn spawn_owned(command: &mut Command) -> std::io::Result<OwnedChild> {
+    let mut owned = owned_pids();
+    let child = command.spawn()?;
+    let pid = child.id().map(|pid| Pid::from_raw(pid as i32));
+    owned.extend(pid);
+    Ok(OwnedChild { child, pid })
+}
+
+/// Kills the process group a child leads if dropped before the child was waited for
+///
+/// Helpers run through `sh -c` in their own process group, so this also
+/// stops the pipelines and background processes they started.
+#[derive(Debug)]
+pub struct GroupGuard(Option<Pid>);
+
+impl GroupGuard {
+    /// Guards the process group led by `child`, which must have been spawned with `process_group(0)`
+    pub fn new(child: &Child) -> Self {
+        Self(child.id().map(|pid| Pid::from_raw(pid as i32)))
+    }
+
+    /// Leaves the group alone from now on, once its leader has been waited for
+    pub fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        if let Some(pgid) = self.0 {
+            if let Err(e) = nix::sys::signal::killpg(pgid, Signal::SIGKILL) {
+                debug!("Failed to kill process group {}: {}", pgid, e);
+            }
+        }
+    }
+}
+
+/// Reaps zombie processes to prevent process table exhaustion
+///
+/// Children started with `spawn_owned` are left alone: their exit status
+/// belongs to the `Child` handle that waits on them, and reaping them here
+/// would make scinit lose the status it has to propagate as its own exit code.
+pub fn reap_zombies() -> Result<()> {
+    let mut reaped_count = 0;
+
+    loop {
+        // Children can't be registered while a status is being taken
+        let owned = owned_pids();
+
+        // Peek at the next waitable child without consuming its status
+        let pid = match waitid(Id::All, WaitPidFlag::WEXITED | WaitPidFlag::WNOHANG | WaitPidFlag::WNOWAIT) {
+            Ok(WaitStatus::StillAlive) => break,
+            Ok(status) => match status.pid() {
+                Some(pid) => pid,
+                None => break,
+            },
+            Err(nix::Error::ECHILD) => break,
+            Err(e) => {
+                warn!("error reaping zombies: {}", e);
+                break;
+            }
+        };
+
+        if owned.contains(&pid) {
+            // An owned child is next in line; its owner will collect it
+            debug!("leaving managed process {} for its owner to reap", pid);
+            break;
+        }
+
+        match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::Exited(pid, status)) => {
+                debug!("reaped zombie process {} with exit status {}", pid, status);
+                reaped_count += 1;
+            }
+            Ok(WaitStatus::Signaled(pid, signal, _)) => {
+                debug!(
+                    "reaped zombie process {} killed by signal {:?}",
+                    pid, signal
+                );
+                reaped_count += 1;
+            }
+            Ok(WaitStatus::StillAlive) => {
+                // Already collected elsewhere
+                break;
+            }
+            Ok(status) => {
+                debug!("ignoring wait status {:?}", status);
+                break;
+            }
+            Err(nix::Error::ECHILD) => {
+                // No child processes
+                break;
+            }
+            Err(e) => {
+                warn!("error reaping zombies: {}", e);
+                break;
+            }
+        }
+    }
+
+    if reaped_count > 0 {
+        debug!("reaped {} zombie processes", reaped_count);
+    }
+
+    Ok(())
+}
+
+/// Converts a child's exit status into the code scinit should exit with
+///
+/// Normal exits keep their code; deaths by signal follow the shell convention
+/// of `128 + signal`, as other container init programs do.
+pub fn exit_code_from_status(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    }
+}
+
+/// Handles child process exit (Scenario A)
+/// 
+/// In container environments, scinit's lifecycle is tied to the child process.
+/// When the child exits, scinit should exit with appropriate logging and status.
+///
+/// # Arguments
+/// * `status` - Exit status of the child
+/// * `oom_killed` - Whether the child was killed by the OOM killer
+///
+/// # Returns
+/// * `Result<i32>` - The exit code derived from the child's status
+pub async fn handle_child_exit(status: std::process::ExitStatus, oom_killed: bool) -> Result<i32> {
+    if oom_killed {
+        info!("Child process was killed by the OOM killer, scinit exiting");
+    } else if status.success() {
+        info!("Child process exited successfully, scinit exiting cleanly");
+    } else if let Some(code) = status.code() {
+        info!("Child process exited with error code {}, scinit exiting", code);
+    } else {
+        // Extract signal information from status
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            info!("Child process terminated by signal {} ({}), scinit exiting", 
+                  signal, signal_name(signal));
+        } else {
+            info!("Child process terminated by signal, scinit exiting");
+        }
+    }
+
+    Ok(exit_code_from_status(status))
+}
+
+/// Reaps zombie processes asynchronously to avoid blocking the main loop
+pub async fn reap_zombies_async() {
+    // Spawn zombie reaping in a blocking task to avoid blocking the main loop
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = reap_zombies() {
+            warn!("error reaping zombies: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::port_manager::PortBindingConfig;
+
+    #[tokio::test]
+    async fn test_process_manager_creation() {
+        let config = ProcessConfig::default();
+        let port_config = PortBindingConfig::default();
+        let port_manager = PortManager::new(port_config);
+        
+        let manager = ProcessManager::new(config, port_manager);
+        assert_eq!(manager.state(), ProcessState::Stopped);
+        assert!(!manager.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_process_spawn() {
+        let config = ProcessConfig {
             command: "echo".to_string(),
             args: vec!["hello".to_string()],
             ..Default::default()
@@ -505,12 +1644,97 @@ mod tests {
         let mut manager = ProcessManager::new(config, port_manager);
         
         // Test file-change restart (should work)
-        let restart_result = manager.restart_process_with_reason("file_change").await.unwrap();
-        assert!(restart_result);
+        let restart_result = manager.restart_process_with_reason(RestartReason::FileChange).await.unwrap();
+        assert_eq!(restart_result, RestartOutcome::Restarted);
         
         // Test crash restart (should fail)
-        let restart_result = manager.restart_process_with_reason("crash").await.unwrap();
-        assert!(!restart_result);
+        let restart_result = manager.restart_process_with_reason(RestartReason::Crash).await.unwrap();
+        assert_eq!(restart_result, RestartOutcome::NotAllowed);
+    }
+
+    #[tokio::test]
+    async fn test_failed_spawn_after_file_change_is_retried() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("app");
+        std::fs::write(&binary, "#!/bin/sh\nexec sleep 10\n").unwrap();
+        let config = ProcessConfig {
+            command: binary.display().to_string(),
+            restart_delay: Duration::from_millis(50),
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+
+        // Not executable yet: the restart is rescheduled instead of failing
+        assert_eq!(manager.restart_process_with_reason(RestartReason::FileChange).await.unwrap(), RestartOutcome::Restarted);
+        assert!(!manager.is_running());
+        assert!(manager.pending_restart().is_some());
+        assert_eq!(manager.pending_restart_reason(), RestartReason::SpawnRetry);
+
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(manager.restart_process_with_reason(manager.pending_restart_reason()).await.unwrap(), RestartOutcome::Restarted);
+        assert!(manager.is_running());
+        assert_eq!(manager.pending_restart_reason(), RestartReason::Crash);
+
+        // Other restarts still report the failure
+        std::fs::remove_file(&binary).unwrap();
+        assert!(manager.restart_process_with_reason(RestartReason::Signal(Signal::SIGHUP)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_spawn_retries_are_charged() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ProcessConfig {
+            command: dir.path().join("missing").display().to_string(),
+            restart_delay: Duration::ZERO,
+            restart_policy: RestartPolicyConfig {
+                initial_backoff: Duration::ZERO,
+                max_restarts: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+
+        // Retries never come back immediately, even without any configured delay
+        let before = tokio::time::Instant::now();
+        assert_eq!(manager.restart_process_with_reason(RestartReason::FileChange).await.unwrap(), RestartOutcome::Restarted);
+        assert!(manager.pending_restart().unwrap() >= before + MIN_SPAWN_RETRY_DELAY);
+        assert_eq!(manager.restart_process_with_reason(RestartReason::SpawnRetry).await.unwrap(), RestartOutcome::Restarted);
+
+        // The third failure exceeds the budget of two restarts
+        assert_eq!(manager.restart_process_with_reason(RestartReason::SpawnRetry).await.unwrap(), RestartOutcome::Exhausted);
+        assert_eq!(manager.state(), ProcessState::Failed);
+        assert!(manager.pending_restart().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_env_file_keeps_running_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let env_file = dir.path().join(".env");
+        std::fs::write(&env_file, "GREETING=hello\n").unwrap();
+        let config = ProcessConfig {
+            command: "sleep".to_string(),
+            args: vec!["10".to_string()],
+            env: EnvConfig {
+                files: vec![env_file.clone()],
+                ..Default::default()
+            },
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let pid = manager.process_info().pid.unwrap();
+
+        std::fs::write(&env_file, "not a valid line\n").unwrap();
+        assert_eq!(manager.restart_process_with_reason(RestartReason::FileChange).await.unwrap(), RestartOutcome::Kept);
+        assert_eq!(manager.process_info().pid, Some(pid));
+        assert!(manager.is_running());
+        assert!(manager.pending_restart().is_none());
+        manager.graceful_shutdown().await.unwrap();
     }
 
     #[tokio::test]
@@ -533,6 +1757,34 @@ mod tests {
         assert_eq!(manager.state(), ProcessState::Stopped);
     }
 
+    #[tokio::test]
+    async fn test_stop_signal_to_child_only() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let marker = temp_dir.path().join("grandchild-signalled");
+        let script = format!(
+            "(trap 'touch {}' USR1; while :; do sleep 0.05; done) & trap 'exit 0' USR1; wait",
+            marker.display()
+        );
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script],
+            graceful_shutdown_timeout: Duration::from_secs(2),
+            stop_signal: Signal::SIGUSR1,
+            stop_target: SignalTarget::Child,
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let pgid = manager.process_info().pgid.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        manager.graceful_shutdown().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let signalled = marker.exists();
+        let _ = nix::sys::signal::killpg(pgid, Signal::SIGKILL);
+        assert!(!signalled, "the grandchild must not receive the stop signal");
+    }
+
     #[tokio::test]
     async fn test_process_info() {
         let config = ProcessConfig {
@@ -599,127 +1851,466 @@ mod tests {
         assert!(manager.should_stop);
         
         // Should not restart after stop
-        let restart_result = manager.restart_process_with_reason("manual").await.unwrap();
-        assert!(!restart_result);
+        let restart_result = manager.restart_process_with_reason(RestartReason::FileChange).await.unwrap();
+        assert_eq!(restart_result, RestartOutcome::NotAllowed);
     }
-}
 
-/// Sets the process group as the foreground process group if a terminal is available
-pub fn process_group_to_foreground(pgid: Pid) -> Result<()> {
-    match File::open("/dev/tty") {
-        Ok(tty) => {
-            if tty.is_terminal() {
-                debug!("Setting process group {} as foreground", &pgid);
-                if let Err(e) = tcsetpgrp(tty, pgid) {
-                    error!("Failed to set process group {} as foreground: {}", &pgid, e);
-                    return Err(e.into());
-                }
-            } else {
-                debug!("Not a terminal, skipping foreground process group setup");
-            }
+    #[tokio::test]
+    async fn test_exit_code_from_status() {
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "exit 3".to_string()],
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let status = manager.wait_for_exit().await.unwrap().unwrap();
+        assert_eq!(exit_code_from_status(status), 3);
+
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "kill -SEGV $$".to_string()],
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let status = manager.wait_for_exit().await.unwrap().unwrap();
+        assert_eq!(exit_code_from_status(status), 128 + libc::SIGSEGV);
+    }
+
+    #[tokio::test]
+    async fn test_crash_restart_policy() {
+        use crate::restart_policy::RestartPolicyConfig;
+
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "exit 1".to_string()],
+            restart_policy: RestartPolicyConfig {
+                policy: RestartPolicy::OnFailure,
+                initial_backoff: Duration::from_millis(10),
+                jitter: 0.0,
+                max_restarts: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let port_manager = PortManager::new(PortBindingConfig::default());
+        let mut manager = ProcessManager::new(config, port_manager);
+
+        manager.spawn_process().await.unwrap();
+        let status = manager.wait_for_exit().await.unwrap().unwrap();
+        assert_eq!(
+            manager.schedule_restart(status),
+            RestartDecision::Restart(Duration::from_millis(10))
+        );
+        assert!(manager.pending_restart().is_some());
+        assert_eq!(manager.restart_process_with_reason(RestartReason::Crash).await.unwrap(), RestartOutcome::Restarted);
+        assert!(manager.pending_restart().is_none());
+
+        // The second failure exceeds the budget of one restart
+        let status = manager.wait_for_exit().await.unwrap().unwrap();
+        assert_eq!(manager.schedule_restart(status), RestartDecision::Exhausted);
+        assert_eq!(manager.state(), ProcessState::Failed);
+    }
+
+    #[test]
+    fn test_subreaper_adopts_orphans() {
+        run_isolated("process_manager::tests::subreaper_adopting_orphans");
+    }
+
+    #[tokio::test]
+    #[ignore = "makes the test process a subreaper for every test's orphans; run by test_subreaper_adopts_orphans"]
+    async fn subreaper_adopting_orphans() {
+        if std::env::var_os("SCINIT_ISOLATED_TEST").is_none() {
+            return;
         }
-        Err(e) => {
-            debug!(
-                "Cannot open /dev/tty ({}), skipping foreground process group setup",
-                e
-            );
+        assert_eq!(setup_reaping(true).unwrap(), ReapingMode::Subreaper);
+        assert!(nix::sys::prctl::get_child_subreaper().unwrap());
+
+        // The shell exits right away, orphaning its background sleep onto us
+        let pid_file = tempfile::NamedTempFile::new().unwrap();
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("sleep 0.2 & echo $! > {}", pid_file.path().display()),
+            ],
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        manager.wait_for_exit().await.unwrap();
+
+        let orphan: i32 = std::fs::read_to_string(pid_file.path()).unwrap().trim().parse().unwrap();
+        let status = tokio::task::spawn_blocking(move || waitpid(Pid::from_raw(orphan), None))
+            .await
+            .unwrap();
+        assert!(matches!(status, Ok(WaitStatus::Exited(_, 0))));
+    }
+
+    #[tokio::test]
+    async fn test_socket_activation() {
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                concat!(
+                    r#"[ "$LISTEN_PID" = "$$" ] && [ "$LISTEN_FDS" = 2 ] && [ "$LISTEN_FDNAMES" = "0:0" ] && "#,
+                    r#"[ "$SCINIT_INHERITED_FDS" = "3,4" ] && "#,
+                    r#"case "$(readlink /proc/$$/fd/3)$(readlink /proc/$$/fd/4)" in socket:*socket:*) ;; *) exit 1;; esac"#,
+                )
+                .to_string(),
+            ],
+            ..Default::default()
+        };
+        let port_config = PortBindingConfig {
+            ports: vec![0, 0],
+            socket_activation: true,
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(port_config));
+
+        manager.spawn_process().await.unwrap();
+        let status = manager.wait_for_exit().await.unwrap().unwrap();
+        assert!(status.success());
+    }
+
+    #[tokio::test]
+    async fn test_sockets_only_reach_their_own_service() {
+        let port_config = PortBindingConfig {
+            ports: vec![0],
+            ..Default::default()
+        };
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                r#"case "$(readlink /proc/$$/fd/$SCINIT_INHERITED_FDS)" in socket:*) ;; *) exit 1;; esac"#.to_string(),
+            ],
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(port_config));
+        manager.spawn_process().await.unwrap();
+        assert!(manager.wait_for_exit().await.unwrap().unwrap().success());
+
+        // Any other child, such as a sibling service, doesn't see the socket
+        let fd = manager.port_manager.get_inherited_fds()[0];
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), format!("[ ! -e /proc/$$/fd/{} ]", fd)],
+            ..Default::default()
+        };
+        let mut sibling = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        sibling.spawn_process().await.unwrap();
+        assert!(sibling.wait_for_exit().await.unwrap().unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_restart() {
+        let config = ProcessConfig {
+            command: "sleep".to_string(),
+            args: vec!["10".to_string()],
+            restart_delay: Duration::from_millis(100),
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            restart_mode: RestartMode::Overlap,
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let old_pid = manager.process_info().pid.unwrap();
+
+        assert_eq!(manager.restart_process_with_reason(RestartReason::FileChange).await.unwrap(), RestartOutcome::Restarted);
+        let new_pid = manager.process_info().pid.unwrap();
+        assert_ne!(old_pid, new_pid);
+        assert!(manager.is_running());
+        assert!(manager.previous.is_none());
+
+        // The old generation has been stopped and collected
+        assert_eq!(nix::sys::signal::kill(old_pid, None), Err(nix::Error::ESRCH));
+        manager.graceful_shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_overlapping_restart_keeps_old_generation() {
+        // Only the first generation starts; later ones exit immediately
+        let temp_dir = tempfile::tempdir().unwrap();
+        let marker = temp_dir.path().join("started");
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("[ -e {0} ] && exit 1; touch {0}; exec sleep 10", marker.display()),
+            ],
+            restart_delay: Duration::from_millis(300),
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            restart_mode: RestartMode::Overlap,
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let old_pid = manager.process_info().pid.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // The kept generation's OOM tracking survives the failed spawn
+        let baseline = Some((PathBuf::from("/sys/fs/cgroup/old"), 7));
+        manager.oom_baseline = baseline.clone();
+
+        assert_eq!(manager.restart_process_with_reason(RestartReason::FileChange).await.unwrap(), RestartOutcome::Kept);
+        assert_eq!(manager.process_info().pid, Some(old_pid));
+        assert!(manager.is_running());
+        assert_eq!(manager.oom_baseline, baseline);
+        assert!(nix::sys::signal::kill(old_pid, None).is_ok());
+        manager.graceful_shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_previous_generation_is_managed() {
+        let config = ProcessConfig {
+            command: "sleep".to_string(),
+            args: vec!["10".to_string()],
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let old_pid = manager.process_info().pid.unwrap();
+
+        // Mid-way through an overlapping restart both generations are tracked
+        let child = manager.child.take().unwrap();
+        manager.previous = Some((old_pid, child, None));
+        manager.spawn_process().await.unwrap();
+        let new_pid = manager.process_info().pid.unwrap();
+        assert!(owned_pids().contains(&new_pid));
+        assert!(owned_pids().contains(&old_pid));
+
+        manager.retire_previous().await;
+        assert!(owned_pids().contains(&new_pid));
+        assert!(!owned_pids().contains(&old_pid));
+        manager.graceful_shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_readiness_probe() {
+        use crate::health::Probe;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let ready_file = temp_dir.path().join("ready");
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), format!("sleep 0.2; touch {}; exec sleep 10", ready_file.display())],
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            readiness: Some(ReadinessConfig {
+                probe: Some(Probe::File(ready_file)),
+                interval: Duration::from_millis(50),
+                timeout: Duration::from_secs(1),
+                startup_timeout: Duration::from_secs(5),
+            }),
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        assert_eq!(manager.state(), ProcessState::Running);
+
+        let pid = manager.process_info().pid.unwrap();
+        let event = timeout(Duration::from_secs(2), manager.next_event()).await.unwrap().unwrap();
+        assert_eq!(event, ProcessEvent::Ready(pid));
+        assert_eq!(manager.state(), ProcessState::Ready);
+        assert!(manager.is_running());
+
+        manager.graceful_shutdown().await.unwrap();
+        assert_eq!(manager.state(), ProcessState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_liveness_failure_restarts_process() {
+        use crate::health::Probe;
+        use crate::restart_policy::{RestartPolicy, RestartPolicyConfig};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = ProcessConfig {
+            command: "sleep".to_string(),
+            args: vec!["10".to_string()],
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            restart_policy: RestartPolicyConfig {
+                policy: RestartPolicy::OnFailure,
+                initial_backoff: Duration::from_millis(10),
+                jitter: 0.0,
+                ..Default::default()
+            },
+            liveness: Some(LivenessConfig {
+                probe: Probe::File(temp_dir.path().join("never-created")),
+                interval: Duration::from_millis(20),
+                timeout: Duration::from_secs(1),
+                failure_threshold: 2,
+                start_period: Duration::ZERO,
+            }),
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+
+        let pid = manager.process_info().pid.unwrap();
+        let event = timeout(Duration::from_secs(2), manager.next_event()).await.unwrap().unwrap();
+        assert_eq!(event, ProcessEvent::Unhealthy(pid));
+
+        let decision = manager.handle_unhealthy().await.unwrap();
+        assert!(matches!(decision, RestartDecision::Restart(_)));
+        assert!(!manager.has_child());
+        assert!(manager.pending_restart().is_some());
+
+        manager.stop();
+    }
+
+    #[tokio::test]
+    async fn test_notify_socket_and_watchdog() {
+        use crate::notify::{Notification, NotifyAddress, NotifyConfig};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let socket_path = temp_dir.path().join("notify.sock");
+        let env_file = temp_dir.path().join("env");
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!("echo \"$NOTIFY_SOCKET $WATCHDOG_USEC\" > {}; exec sleep 10", env_file.display()),
+            ],
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            notify: Some(NotifyConfig {
+                address: NotifyAddress::Path(socket_path.clone()),
+                watchdog: Some(Duration::from_millis(300)),
+            }),
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let pid = manager.process_info().pid.unwrap();
+
+        // Notifications from the process group update the process info
+        assert_eq!(manager.handle_notification(pid, Notification::Status("warming up".to_string())), None);
+        assert_eq!(manager.process_info().status.as_deref(), Some("warming up"));
+        assert_eq!(manager.handle_notification(pid, Notification::Ready), Some(ProcessEvent::Ready(pid)));
+        assert_eq!(manager.state(), ProcessState::Ready);
+
+        // ... but not from unrelated processes
+        assert_eq!(manager.handle_notification(Pid::this(), Notification::WatchdogTrigger), None);
+
+        // Without WATCHDOG=1 the watchdog lapses
+        let event = timeout(Duration::from_secs(2), manager.next_event()).await.unwrap().unwrap();
+        assert_eq!(event, ProcessEvent::Unhealthy(pid));
+
+        let env = std::fs::read_to_string(&env_file).unwrap();
+        assert_eq!(env.trim(), format!("{} 300000", socket_path.display()));
+
+        // A lapsed watchdog restarts the process even under the default `never` policy
+        assert!(matches!(manager.handle_unhealthy().await.unwrap(), RestartDecision::Restart(_)));
+        assert!(manager.pending_restart().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_main_pid_must_belong_to_the_service() {
+        let config = ProcessConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "sleep 10 & wait".to_string()],
+            graceful_shutdown_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let mut manager = ProcessManager::new(config, PortManager::new(PortBindingConfig::default()));
+        manager.spawn_process().await.unwrap();
+        let pid = manager.process_info().pid.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Neither init nor scinit itself may be claimed as the main process
+        for unrelated in [Pid::from_raw(1), Pid::this()] {
+            assert_eq!(manager.handle_notification(pid, Notification::MainPid(unrelated)), None);
+            assert_eq!(manager.process_info().pid, Some(pid));
         }
+
+        // A process in the service's group may
+        let sleep = std::fs::read_to_string(format!("/proc/{}/task/{}/children", pid, pid)).unwrap();
+        let sleep = Pid::from_raw(sleep.split_whitespace().next().unwrap().parse().unwrap());
+        manager.handle_notification(pid, Notification::MainPid(sleep));
+        assert_eq!(manager.process_info().pid, Some(sleep));
+
+        manager.graceful_shutdown().await.unwrap();
     }
-    Ok(())
-}
 
-/// Reaps zombie processes to prevent process table exhaustion
-pub fn reap_zombies() -> Result<()> {
-    let mut reaped_count = 0;
+    /// Runs an ignored test alone in a fresh copy of the test binary
+    ///
+    /// Zombie reaping takes every child of the process, and a subreaper
+    /// adopts every orphan, including those of tests running next to it.
+    fn run_isolated(test: &str) {
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([test, "--exact", "--ignored", "--quiet"])
+            .env("SCINIT_ISOLATED_TEST", "1")
+            .status()
+            .unwrap();
+        assert!(status.success(), "{} failed", test);
+    }
 
-    loop {
-        match waitpid(None, Some(WaitPidFlag::WNOHANG)) {
-            Ok(WaitStatus::Exited(pid, status)) => {
-                debug!("reaped zombie process {} with exit status {}", pid, status);
-                reaped_count += 1;
-            }
-            Ok(WaitStatus::Signaled(pid, signal, _)) => {
-                debug!(
-                    "reaped zombie process {} killed by signal {:?}",
-                    pid, signal
-                );
-                reaped_count += 1;
-            }
-            Ok(WaitStatus::Stopped(pid, signal)) => {
-                debug!("reaped stopped process {} by signal {:?}", pid, signal);
-                reaped_count += 1;
-            }
-            Ok(WaitStatus::Continued(pid)) => {
-                debug!("reaped continued process {}", pid);
-                reaped_count += 1;
-            }
-            Ok(WaitStatus::StillAlive) => {
-                // No more zombies to reap
-                break;
-            }
-            #[cfg(target_os = "linux")]
-            Ok(WaitStatus::PtraceEvent(_, _, _)) | Ok(WaitStatus::PtraceSyscall(_)) => {
-                // Ignore ptrace events
-                continue;
-            }
-            Err(nix::Error::ECHILD) => {
-                // No child processes
-                break;
-            }
-            Err(e) => {
-                warn!("error reaping zombies: {}", e);
-                break;
+    /// Keeps reaping zombies until the returned flag is cleared
+    fn spawn_reaper() -> (std::sync::Arc<std::sync::atomic::AtomicBool>, std::thread::JoinHandle<()>) {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        let reaping = std::sync::Arc::new(AtomicBool::new(true));
+        let flag = reaping.clone();
+        let reaper = std::thread::spawn(move || {
+            while flag.load(Ordering::Relaxed) {
+                reap_zombies().unwrap();
+                std::thread::yield_now();
             }
+        });
+        (reaping, reaper)
+    }
+
+    #[test]
+    fn test_reaper_leaves_exec_probes_alone() {
+        run_isolated("process_manager::tests::reaper_with_exec_probes");
+    }
+
+    #[tokio::test]
+    #[ignore = "reaps every child of the test process; run by test_reaper_leaves_exec_probes_alone"]
+    async fn reaper_with_exec_probes() {
+        if std::env::var_os("SCINIT_ISOLATED_TEST").is_none() {
+            return;
         }
+        let (reaping, reaper) = spawn_reaper();
+        let probe = crate::health::Probe::Exec("sleep 0.01".to_string());
+        for _ in 0..30 {
+            probe.check(Duration::from_secs(2)).await.unwrap();
+        }
+        reaping.store(false, std::sync::atomic::Ordering::Relaxed);
+        reaper.join().unwrap();
     }
 
-    if reaped_count > 0 {
-        debug!("reaped {} zombie processes", reaped_count);
+    #[test]
+    fn test_reaper_leaves_builds_alone() {
+        run_isolated("process_manager::tests::reaper_with_builds");
     }
 
-    Ok(())
-}
+    #[tokio::test]
+    #[ignore = "reaps every child of the test process; run by test_reaper_leaves_builds_alone"]
+    async fn reaper_with_builds() {
+        use crate::file_watcher::{FileChangeEvent, FileWatchConfig, FileWatcher};
 
-/// Handles child process exit (Scenario A)
-/// 
-/// In container environments, scinit's lifecycle is tied to the child process.
-/// When the child exits, scinit should exit with appropriate logging and status.
-pub async fn handle_child_exit(status: std::process::ExitStatus) -> Result<()> {
-    if status.success() {
-        info!("Child process exited successfully, scinit exiting cleanly");
-    } else if let Some(code) = status.code() {
-        info!("Child process exited with error code {}, scinit exiting", code);
-    } else {
-        // Extract signal information from status
-        #[cfg(unix)]
-        {
-            use std::os::unix::process::ExitStatusExt;
-            if let Some(signal) = status.signal() {
-                info!("Child process terminated by signal {} ({}), scinit exiting", 
-                      signal, signal_name(signal));
-            } else {
-                info!("Child process terminated by signal, scinit exiting");
-            }
+        if std::env::var_os("SCINIT_ISOLATED_TEST").is_none() {
+            return;
         }
-        #[cfg(not(unix))]
-        {
-            info!("Child process terminated by signal, scinit exiting");
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut watcher = FileWatcher::new(FileWatchConfig {
+            watch_paths: vec![temp_dir.path().to_path_buf()],
+            debounce_ms: 10,
+            on_change_run: Some("sleep 0.01".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        watcher.start_watching().await.unwrap();
+
+        let (reaping, reaper) = spawn_reaper();
+        for i in 0..50 {
+            std::fs::write(temp_dir.path().join("main.rs"), i.to_string()).unwrap();
+            let event = timeout(Duration::from_secs(2), watcher.next_event()).await;
+            assert!(matches!(event, Ok(Some(FileChangeEvent::FilesChanged(_)))), "build {} was lost", i);
         }
+        reaping.store(false, std::sync::atomic::Ordering::Relaxed);
+        reaper.join().unwrap();
     }
-    
-    // Reap any remaining zombies before exiting
-    debug!("Reaping any remaining zombie processes before exit");
-    reap_zombies_async().await;
-    
-    Ok(())
 }
-
-/// Reaps zombie processes asynchronously to avoid blocking the main loop
-pub async fn reap_zombies_async() {
-    // Spawn zombie reaping in a blocking task to avoid blocking the main loop
-    tokio::task::spawn_blocking(|| {
-        if let Err(e) = reap_zombies() {
-            warn!("error reaping zombies: {}", e);
-        }
-    });
-}
\ No newline at end of file
@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+/// Exit code used by scinit when the restart budget has been exhausted
+///
+/// Chosen apart from the codes that container runtimes (125-127 for `docker
+/// run` and podman), shells (126, 127 and 128+signal) and systemd (200 and
+/// up) report for their own failures. A child may still exit with it, so it
+/// can be changed with `--remap-exit exhausted=CODE`.
+pub const EXIT_CODE_RESTARTS_EXHAUSTED: i32 = 122;
+
+/// When the managed process should be restarted after it exits on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RestartPolicy {
+    /// Never restart; scinit exits together with the child
+    Never,
+    /// Restart only when the child exits unsuccessfully or is killed by a signal
+    OnFailure,
+    /// Restart whenever the child exits
+    Always,
+}
+
+impl RestartPolicy {
+    /// Checks whether this policy asks for a restart after the given exit
+    pub fn applies_to(&self, status: ExitStatus) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !status.success(),
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// Configuration for crash restarts
+#[derive(Debug, Clone)]
+pub struct RestartPolicyConfig {
+    /// Which exits trigger a restart
+    pub policy: RestartPolicy,
+    /// Delay before the first restart in a window
+    pub initial_backoff: Duration,
+    /// Upper bound for the backoff delay
+    pub max_backoff: Duration,
+    /// Random spread applied to each delay, as a fraction of the delay (0.0 - 1.0)
+    pub jitter: f64,
+    /// Number of restarts allowed within `window`
+    pub max_restarts: u32,
+    /// Sliding window the restart budget applies to
+    pub window: Duration,
+}
+
+impl Default for RestartPolicyConfig {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::Never,
+            initial_backoff: Duration::from_millis(1000),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.1,
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Outcome of consulting the restart policy after a child exit
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestartDecision {
+    /// Restart the process after the given delay
+    Restart(Duration),
+    /// The policy doesn't restart on this exit; scinit should exit
+    Exit,
+    /// The restart budget for the current window is used up
+    Exhausted,
+}
+
+/// Tracks restarts against the configured budget and computes backoff delays
+///
+/// The backoff grows exponentially with the number of restarts still inside
+/// the budget window, so a process that stays up for a full window starts
+/// over at the initial delay.
+#[derive(Debug)]
+pub struct RestartTracker {
+    /// Restart policy configuration
+    config: RestartPolicyConfig,
+    /// Times of the restarts inside the current window
+    restarts: VecDeque<Instant>,
+}
+
+impl RestartTracker {
+    /// Creates a new tracker for the given policy
+    pub fn new(config: RestartPolicyConfig) -> Self {
+        Self {
+            config,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    /// Gets the configured restart policy
+    pub fn policy(&self) -> RestartPolicy {
+        self.config.policy
+    }
+
+    /// Number of restarts recorded in the current window
+    pub fn restarts_in_window(&self) -> usize {
+        self.restarts.len()
+    }
+
+    /// Decides what to do after the child exited with `status`
+    ///
+    /// A `Restart` decision is recorded against the budget immediately.
+    ///
+    /// # Arguments
+    /// * `status` - Exit status of the child
+    /// * `now` - Current time, used for the sliding budget window
+    pub fn decide(&mut self, status: ExitStatus, now: Instant) -> RestartDecision {
+        if !self.config.policy.applies_to(status) {
+            return RestartDecision::Exit;
+        }
+        self.charge(now)
+    }
+
+    /// Decides what to do after the process was stopped for failing a health check
+    ///
+    /// The stop always counts as a failure, whatever exit status the process
+    /// reported when it was terminated.
+    ///
+    /// # Arguments
+    /// * `now` - Current time, used for the sliding budget window
+    pub fn decide_unhealthy(&mut self, now: Instant) -> RestartDecision {
+        if self.config.policy == RestartPolicy::Never {
+            return RestartDecision::Exit;
+        }
+        self.charge(now)
+    }
+
+    /// Records a restart against the budget, unless it is used up
+    ///
+    /// Unlike `decide`, this ignores the policy; it is for callers that want
+    /// the process restarted no matter how it exited.
+    ///
+    /// # Arguments
+    /// * `now` - Current time, used for the sliding budget window
+    pub fn charge(&mut self, now: Instant) -> RestartDecision {
+        // Forget restarts that fell out of the window
+        while let Some(&oldest) = self.restarts.front() {
+            if now.duration_since(oldest) >= self.config.window {
+                self.restarts.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if self.restarts.len() >= self.config.max_restarts as usize {
+            return RestartDecision::Exhausted;
+        }
+
+        let delay = self.backoff(self.restarts.len() as u32);
+        self.restarts.push_back(now);
+        RestartDecision::Restart(delay)
+    }
+
+    /// Computes the jittered backoff delay for the given attempt
+    fn backoff(&self, attempt: u32) -> Duration {
+        let base = self
+            .config
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.config.max_backoff);
+
+        let jitter = self.config.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return base;
+        }
+
+        // Spread the delay uniformly over [base * (1 - jitter), base * (1 + jitter)]
+        let factor = 1.0 - jitter + 2.0 * jitter * fastrand::f64();
+        base.mul_f64(factor).min(self.config.max_backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    fn config(policy: RestartPolicy) -> RestartPolicyConfig {
+        RestartPolicyConfig {
+            policy,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: 0.0,
+            max_restarts: 3,
+            window: Duration::from_secs(10),
+        }
+    }
+
+    fn exited(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[test]
+    fn test_policy_applies_to() {
+        let killed = ExitStatus::from_raw(libc::SIGKILL);
+
+        assert!(!RestartPolicy::Never.applies_to(exited(1)));
+        assert!(RestartPolicy::OnFailure.applies_to(exited(1)));
+        assert!(RestartPolicy::OnFailure.applies_to(killed));
+        assert!(!RestartPolicy::OnFailure.applies_to(exited(0)));
+        assert!(RestartPolicy::Always.applies_to(exited(0)));
+    }
+
+    #[test]
+    fn test_exponential_backoff_is_capped() {
+        let mut tracker = RestartTracker::new(RestartPolicyConfig {
+            max_restarts: 10,
+            ..config(RestartPolicy::Always)
+        });
+        let now = Instant::now();
+
+        let delays: Vec<_> = (0..5)
+            .map(|_| match tracker.decide(exited(1), now) {
+                RestartDecision::Restart(delay) => delay.as_millis(),
+                other => panic!("unexpected decision {:?}", other),
+            })
+            .collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+    }
+
+    #[test]
+    fn test_budget_exhaustion_and_window() {
+        let mut tracker = RestartTracker::new(config(RestartPolicy::OnFailure));
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(matches!(tracker.decide(exited(1), now), RestartDecision::Restart(_)));
+        }
+        assert_eq!(tracker.decide(exited(1), now), RestartDecision::Exhausted);
+
+        // Once the window has passed the budget and the backoff start over
+        let later = now + Duration::from_secs(11);
+        assert_eq!(
+            tracker.decide(exited(1), later),
+            RestartDecision::Restart(Duration::from_millis(100))
+        );
+        assert_eq!(tracker.restarts_in_window(), 1);
+    }
+
+    #[test]
+    fn test_unhealthy_counts_as_failure() {
+        let mut tracker = RestartTracker::new(config(RestartPolicy::OnFailure));
+        let now = Instant::now();
+
+        assert!(matches!(tracker.decide_unhealthy(now), RestartDecision::Restart(_)));
+        assert_eq!(tracker.restarts_in_window(), 1);
+
+        let mut never = RestartTracker::new(config(RestartPolicy::Never));
+        assert_eq!(never.decide_unhealthy(now), RestartDecision::Exit);
+    }
+
+    #[test]
+    fn test_jitter_stays_in_bounds() {
+        let mut tracker = RestartTracker::new(RestartPolicyConfig {
+            jitter: 0.5,
+            max_restarts: 100,
+            window: Duration::from_millis(1),
+            ..config(RestartPolicy::Always)
+        });
+        let start = Instant::now();
+
+        for i in 0..50 {
+            // Each decision lands in a fresh window, so the base delay stays at 100ms
+            let now = start + Duration::from_millis(10 * i);
+            match tracker.decide(exited(0), now) {
+                RestartDecision::Restart(delay) => {
+                    assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+                }
+                other => panic!("unexpected decision {:?}", other),
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use super::Result;
+use eyre::eyre;
+use nix::sys::resource::{getrlimit, setrlimit, Resource};
+use std::fmt;
+use std::str::FromStr;
+
+/// Value meaning "no limit"
+const UNLIMITED: u64 = libc::RLIM_INFINITY;
+
+/// Capability needed to raise a hard limit
+const CAP_SYS_RESOURCE: u32 = 24;
+
+/// Resource names accepted by `--rlimit`, as in `ulimit` and `prlimit`
+const RESOURCES: &[(&str, Resource)] = &[
+    ("as", Resource::RLIMIT_AS),
+    ("core", Resource::RLIMIT_CORE),
+    ("cpu", Resource::RLIMIT_CPU),
+    ("data", Resource::RLIMIT_DATA),
+    ("fsize", Resource::RLIMIT_FSIZE),
+    ("locks", Resource::RLIMIT_LOCKS),
+    ("memlock", Resource::RLIMIT_MEMLOCK),
+    ("msgqueue", Resource::RLIMIT_MSGQUEUE),
+    ("nice", Resource::RLIMIT_NICE),
+    ("nofile", Resource::RLIMIT_NOFILE),
+    ("nproc", Resource::RLIMIT_NPROC),
+    ("rss", Resource::RLIMIT_RSS),
+    ("rtprio", Resource::RLIMIT_RTPRIO),
+    ("rttime", Resource::RLIMIT_RTTIME),
+    ("sigpending", Resource::RLIMIT_SIGPENDING),
+    ("stack", Resource::RLIMIT_STACK),
+];
+
+/// A resource limit applied to the managed process, written as `NAME=SOFT[:HARD]`
+///
+/// Values are numbers in the resource's own unit or `unlimited`; without a
+/// hard value both limits are set to the soft value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RlimitSpec {
+    /// Limited resource
+    pub resource: Resource,
+    /// Soft limit
+    pub soft: u64,
+    /// Hard limit
+    pub hard: u64,
+}
+
+impl RlimitSpec {
+    /// Name of the limited resource
+    pub fn name(&self) -> &'static str {
+        RESOURCES
+            .iter()
+            .find(|(_, resource)| *resource == self.resource)
+            .map(|(name, _)| *name)
+            .unwrap_or("unknown")
+    }
+
+    /// Checks that the limit can be applied from scinit's position
+    ///
+    /// Raising a hard limit above scinit's own needs `CAP_SYS_RESOURCE`, and
+    /// the kernel caps `nofile` at `fs.nr_open` even then. Checking up front
+    /// turns an opaque spawn failure into an error naming the limit.
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success, or an error explaining why the limit can't be set
+    pub fn validate(&self) -> Result<()> {
+        if self.soft > self.hard {
+            return Err(eyre!("Invalid rlimit {}: soft limit exceeds hard limit", self));
+        }
+
+        let (_, current_hard) = getrlimit(self.resource)
+            .map_err(|e| eyre!("Failed to read the {} limit: {}", self.name(), e))?;
+        if self.hard > current_hard && !has_capability(CAP_SYS_RESOURCE) {
+            return Err(eyre!(
+                "Cannot set rlimit {}: raising the hard limit above scinit's own ({}) requires CAP_SYS_RESOURCE",
+                self,
+                format_limit(current_hard)
+            ));
+        }
+
+        if self.resource == Resource::RLIMIT_NOFILE {
+            if let Some(nr_open) = read_nr_open() {
+                if self.hard > nr_open {
+                    return Err(eyre!("Cannot set rlimit {}: the kernel allows at most {} (fs.nr_open)", self, nr_open));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the limit on the calling process
+    ///
+    /// Meant for the child between fork and exec.
+    pub fn apply(&self) -> nix::Result<()> {
+        setrlimit(self.resource, self.soft, self.hard)
+    }
+}
+
+impl fmt::Display for RlimitSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}:{}", self.name(), format_limit(self.soft), format_limit(self.hard))
+    }
+}
+
+impl FromStr for RlimitSpec {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, values) = s
+            .split_once('=')
+            .ok_or_else(|| eyre!("Invalid rlimit '{}': expected NAME=SOFT[:HARD]", s))?;
+        let resource = RESOURCES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, resource)| *resource)
+            .ok_or_else(|| {
+                let names: Vec<_> = RESOURCES.iter().map(|(name, _)| *name).collect();
+                eyre!("Invalid rlimit '{}': unknown resource '{}', expected one of {}", s, name, names.join(", "))
+            })?;
+
+        let parse = |value: &str| -> Result<u64> {
+            match value.trim() {
+                "unlimited" | "infinity" => Ok(UNLIMITED),
+                value => value.parse().map_err(|e| eyre!("Invalid rlimit value '{}' in '{}': {}", value, s, e)),
+            }
+        };
+        let (soft, hard) = match values.split_once(':') {
+            Some((soft, hard)) => (parse(soft)?, parse(hard)?),
+            None => {
+                let value = parse(values)?;
+                (value, value)
+            }
+        };
+        Ok(RlimitSpec { resource, soft, hard })
+    }
+}
+
+fn format_limit(value: u64) -> String {
+    if value == UNLIMITED {
+        "unlimited".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Checks the effective capability set of scinit itself
+fn has_capability(cap: u32) -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            let caps = status.lines().find_map(|line| line.strip_prefix("CapEff:"))?;
+            u64::from_str_radix(caps.trim(), 16).ok()
+        })
+        .is_some_and(|caps| caps & (1 << cap) != 0)
+}
+
+/// Kernel ceiling for `nofile`
+fn read_nr_open() -> Option<u64> {
+    std::fs::read_to_string("/proc/sys/fs/nr_open").ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rlimit_parsing() {
+        let spec: RlimitSpec = "nofile=1024:65536".parse().unwrap();
+        assert_eq!(spec.resource, Resource::RLIMIT_NOFILE);
+        assert_eq!((spec.soft, spec.hard), (1024, 65536));
+        assert_eq!(spec.to_string(), "nofile=1024:65536");
+
+        let spec: RlimitSpec = "core=unlimited".parse().unwrap();
+        assert_eq!((spec.soft, spec.hard), (UNLIMITED, UNLIMITED));
+        assert_eq!(spec.to_string(), "core=unlimited:unlimited");
+
+        assert!("nofile".parse::<RlimitSpec>().is_err());
+        assert!("files=10".parse::<RlimitSpec>().is_err());
+        assert!("nofile=ten".parse::<RlimitSpec>().is_err());
+    }
+
+    #[test]
+    fn test_rlimit_validation() {
+        let (soft, hard) = getrlimit(Resource::RLIMIT_NOFILE).unwrap();
+        let current = RlimitSpec { resource: Resource::RLIMIT_NOFILE, soft, hard };
+        assert!(current.validate().is_ok());
+
+        let inverted = RlimitSpec { soft: 2, hard: 1, ..current };
+        assert!(inverted.validate().is_err());
+
+        let too_many = RlimitSpec { soft: 1, hard: UNLIMITED, ..current };
+        assert!(too_many.validate().is_err());
+    }
+}
@@ -1,43 +1,265 @@
 use super::Result;
-use crate::process_manager::ProcessManager;
+use crate::process_manager::RestartMode;
+use crate::supervisor::Supervisor;
 
 pub use nix::sys::signal::Signal;
 
+use eyre::eyre;
 use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet};
 #[cfg(target_os = "linux")]
 use nix::sys::signalfd::{SfdFlags, SignalFd};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use futures_core::Stream;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use std::time::Duration;
+#[cfg(target_os = "linux")]
+use tokio::io::unix::AsyncFd;
 use tracing::{debug, error, info, warn};
 
 /// Converts signal number to human-readable name
-pub fn signal_name(signal: i32) -> &'static str {
+///
+/// Real-time signals are named relative to the nearer end of the range, as
+/// `kill -l` does: SIGRTMIN, SIGRTMIN+1, ..., SIGRTMAX-1, SIGRTMAX.
+pub fn signal_name(signal: i32) -> Cow<'static, str> {
+    if let Ok(signal) = Signal::try_from(signal) {
+        return Cow::Borrowed(signal.as_str());
+    }
+    let (min, max) = realtime_signal_range();
     match signal {
-        2 => "SIGINT",
-        9 => "SIGKILL",
-        15 => "SIGTERM",
-        3 => "SIGQUIT",
-        1 => "SIGHUP",
-        10 => "SIGUSR1",
-        12 => "SIGUSR2",
-        17 => "SIGCHLD",
-        _ => "UNKNOWN",
+        _ if signal == min => Cow::Borrowed("SIGRTMIN"),
+        _ if signal == max => Cow::Borrowed("SIGRTMAX"),
+        _ if signal > min && signal <= (min + max) / 2 => Cow::Owned(format!("SIGRTMIN+{}", signal - min)),
+        _ if signal > min && signal < max => Cow::Owned(format!("SIGRTMAX-{}", max - signal)),
+        _ => Cow::Owned(format!("SIG{}", signal)),
+    }
+}
+
+/// Range of real-time signals available to applications
+///
+/// The C library reserves the first few kernel real-time signals for itself,
+/// so this starts above the kernel's SIGRTMIN.
+#[cfg(target_os = "linux")]
+pub fn realtime_signal_range() -> (i32, i32) {
+    (libc::SIGRTMIN(), libc::SIGRTMAX())
+}
+
+/// Range of real-time signals available to applications (none on this platform)
+#[cfg(not(target_os = "linux"))]
+pub fn realtime_signal_range() -> (i32, i32) {
+    (0, -1)
+}
+
+/// Parses a signal given as `SIGTERM`, `TERM` (any case) or a number
+pub fn parse_signal(s: &str) -> Result<Signal> {
+    let s = s.trim();
+    if let Ok(number) = s.parse::<i32>() {
+        return Signal::try_from(number).map_err(|_| eyre!("Invalid signal number {}", number));
+    }
+    let name = s.to_ascii_uppercase();
+    let name = if name.starts_with("SIG") { name } else { format!("SIG{}", name) };
+    Signal::from_str(&name).map_err(|_| eyre!("Unknown signal '{}'", s))
+}
+
+/// Parses a signal that may also be a real-time one
+///
+/// Accepts everything `parse_signal` does, plus `SIGRTMIN`, `SIGRTMAX`,
+/// `SIGRTMIN+N`, `SIGRTMAX-N` (with or without the `SIG` prefix) and the
+/// numbers of real-time signals.
+pub fn parse_signal_number(s: &str) -> Result<i32> {
+    if let Ok(signal) = parse_signal(s) {
+        return Ok(signal as i32);
+    }
+    let s = s.trim();
+    let (min, max) = realtime_signal_range();
+    let name = s.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    let offset = |rest: &str, sign: char| -> Option<i32> {
+        if rest.is_empty() {
+            return Some(0);
+        }
+        rest.strip_prefix(sign)?.parse().ok()
+    };
+    let signal = if let Ok(number) = s.parse::<i32>() {
+        Some(number)
+    } else if let Some(rest) = name.strip_prefix("RTMIN") {
+        offset(rest, '+').map(|n| min + n)
+    } else if let Some(rest) = name.strip_prefix("RTMAX") {
+        offset(rest, '-').map(|n| max - n)
+    } else {
+        None
+    };
+    let signal = signal.ok_or_else(|| eyre!("Unknown signal '{}'", s))?;
+    if !(min..=max).contains(&signal) {
+        return Err(eyre!("Invalid signal '{}': outside the real-time range {}-{}", s, min, max));
+    }
+    Ok(signal)
+}
+
+/// Where a forwarded signal is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SignalTarget {
+    /// The whole process group of the service
+    Group,
+    /// Only the service's main process
+    Child,
+}
+
+/// How signals received by scinit are passed on to the services
+///
+/// Signals are keyed by number, so the policy covers real-time signals too.
+#[derive(Debug, Clone)]
+pub struct SignalPolicy {
+    /// Signals replaced before forwarding; None drops the signal entirely
+    pub rewrites: HashMap<i32, Option<i32>>,
+    /// Delivery target per forwarded signal (after rewriting); the process group by default
+    pub targets: HashMap<i32, SignalTarget>,
+    /// Received signals that restart the services instead of being forwarded
+    pub restart_signals: Vec<Signal>,
+    /// How services are replaced on a restart signal
+    pub restart_mode: RestartMode,
+    /// Whether scinit stops itself after forwarding SIGTSTP
+    pub job_control: bool,
+}
+
+impl Default for SignalPolicy {
+    fn default() -> Self {
+        Self {
+            rewrites: HashMap::new(),
+            targets: HashMap::new(),
+            restart_signals: Vec::new(),
+            restart_mode: RestartMode::StopStart,
+            job_control: false,
+        }
+    }
+}
+
+impl SignalPolicy {
+    /// Signal to forward in place of a received one, or None to drop it
+    pub fn rewrite(&self, signal: i32) -> Option<i32> {
+        self.rewrites.get(&signal).copied().unwrap_or(Some(signal))
+    }
+
+    /// Where a forwarded signal is delivered
+    pub fn target(&self, signal: i32) -> SignalTarget {
+        self.targets.get(&signal).copied().unwrap_or(SignalTarget::Group)
     }
 }
 
+/// Parses a signal rewrite of the form `FROM=TO`, where TO may be `0` or `none` to drop the signal
+pub fn parse_signal_rewrite(s: &str) -> Result<(i32, Option<i32>)> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| eyre!("Invalid signal rewrite '{}': expected FROM=TO", s))?;
+    let to = match to.trim() {
+        "0" | "none" => None,
+        to => Some(parse_signal_number(to)?),
+    };
+    Ok((parse_signal_number(from)?, to))
+}
+
+/// Parses a signal target of the form `SIGNAL=group|child`
+pub fn parse_signal_target(s: &str) -> Result<(i32, SignalTarget)> {
+    let (signal, target) = s
+        .split_once('=')
+        .ok_or_else(|| eyre!("Invalid signal target '{}': expected SIGNAL=group|child", s))?;
+    let target = match target.trim() {
+        "group" => SignalTarget::Group,
+        "child" => SignalTarget::Child,
+        other => return Err(eyre!("Invalid signal target '{}' in '{}': expected group or child", other, s)),
+    };
+    Ok((parse_signal_number(signal)?, target))
+}
+
+/// Signals scinit never routes through the signalfd
+///
+/// - SIGKILL, SIGSTOP: can't be caught or blocked
+/// - SIGCHLD: left to tokio's child reaper (the handler adds it for its own thread)
+/// - SIGSEGV, SIGBUS, SIGILL, SIGFPE, SIGTRAP, SIGSYS, SIGABRT: raised by faults in
+///   scinit itself, where blocking them would hide the crash
+/// - SIGPIPE: raised by scinit's own writes to closed pipes, and ignored by Rust
+/// - SIGTTIN, SIGTTOU: ignored so terminal access never stops scinit
+const UNROUTED_SIGNALS: [Signal; 13] = [
+    Signal::SIGKILL,
+    Signal::SIGSTOP,
+    Signal::SIGCHLD,
+    Signal::SIGSEGV,
+    Signal::SIGBUS,
+    Signal::SIGILL,
+    Signal::SIGFPE,
+    Signal::SIGTRAP,
+    Signal::SIGSYS,
+    Signal::SIGABRT,
+    Signal::SIGPIPE,
+    Signal::SIGTTIN,
+    Signal::SIGTTOU,
+];
+
+/// Builds the set of signals scinit receives and forwards instead of acting on directly.
+///
+/// This is every catchable signal apart from [`UNROUTED_SIGNALS`], including
+/// the real-time signals. What happens to each one is decided in
+/// [`SignalHandler::process_signal`]:
+/// - SIGTERM, SIGINT, SIGQUIT, SIGPWR: graceful shutdown
+/// - SIGTSTP: forwarded; with job control, scinit then stops itself unless it is PID 1
+/// - everything else, real-time signals included: forwarded to the services
+fn forwarded_signal_set() -> SigSet {
+    let mut signals: SigSet = Signal::iterator()
+        .filter(|signal| !UNROUTED_SIGNALS.contains(signal))
+        .collect();
+
+    #[cfg(target_os = "linux")]
+    {
+        // Real-time signals have no `Signal` variant, so add them to the raw set
+        let mut raw = *signals.as_ref();
+        let (min, max) = realtime_signal_range();
+        for signal in min..=max {
+            // SAFETY: `raw` is an initialized sigset_t and `signal` is a valid signal number
+            unsafe { libc::sigaddset(&mut raw, signal) };
+        }
+        // SAFETY: `raw` was initialized from a valid SigSet
+        signals = unsafe { SigSet::from_sigset_t_unchecked(raw) };
+    }
+    signals
+}
+
+/// Blocks the forwarded signals on the calling thread.
+///
+/// Must be called before the async runtime starts so that every worker thread
+/// inherits the mask; otherwise a process-directed SIGTERM can land on a worker
+/// with the default disposition and kill scinit before it can forward it.
+/// SIGCHLD is deliberately left unblocked here because tokio's child reaper
+/// relies on receiving it.
+pub fn block_forwarded_signals() -> Result<()> {
+    forwarded_signal_set().thread_block()?;
+    Ok(())
+}
+
 /// Signal handler for the init system with proper init semantics.
 ///
 /// This handler uses platform-appropriate signal handling that maintains
 /// proper init system semantics: synchronous, deterministic signal processing
 /// with guaranteed delivery order.
 ///
-/// - Linux: Uses SignalFd for safe, synchronous signal handling
-/// - Other platforms: Uses sigtimedwait for proper init system semantics
+/// Received signal numbers are delivered as a [`Stream`], so the event loop
+/// wakes exactly when a signal arrives:
+/// - Linux: Uses a SignalFd registered with the tokio reactor
+/// - Other platforms: Uses sigwait on a blocking thread, one wait at a time
 pub(super) struct SignalHandler {
     /// Set of signals we handle (blocked for synchronous handling)
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
     handled_signals: SigSet,
+    /// Rewriting and delivery of forwarded signals
+    policy: SignalPolicy,
     /// SignalFd for reading blocked signals safely (Linux only)
     #[cfg(target_os = "linux")]
-    signal_fd: SignalFd,
+    signal_fd: AsyncFd<SignalFd>,
+    /// Outstanding sigwait; kept across polls so a signal is never consumed by an abandoned wait
+    #[cfg(not(target_os = "linux"))]
+    pending_wait: Option<tokio::task::JoinHandle<nix::Result<Signal>>>,
 }
 
 impl SignalHandler {
@@ -48,29 +270,14 @@ impl SignalHandler {
     /// - Leaves critical signals (SIGFPE, SIGILL, etc.) unblocked
     /// - Uses platform-appropriate synchronous signal handling
     /// - Maintains proper init system semantics across platforms
-    pub fn new() -> Result<Self> {
-        // Create signal set with the signals we want to handle
-        let mut handled_signals = SigSet::empty();
-
-        // Signals that init should handle synchronously:
-        // - SIGTERM, SIGINT, SIGQUIT: Termination signals for graceful shutdown
-        // - SIGUSR1, SIGUSR2: User-defined signals to forward
-        // - SIGHUP: Hangup signal to forward
-        // - SIGCHLD: Child status changes (always handled by init)
-        let signals_to_handle = [
-            Signal::SIGTERM,
-            Signal::SIGINT,
-            Signal::SIGQUIT,
-            Signal::SIGUSR1,
-            Signal::SIGUSR2,
-            Signal::SIGHUP,
-            Signal::SIGCHLD,
-        ];
-
-        // Add signals to the set
-        for &sig in &signals_to_handle {
-            handled_signals.add(sig);
-        }
+    ///
+    /// # Arguments
+    /// * `policy` - Rewriting and delivery of forwarded signals
+    pub fn new(policy: SignalPolicy) -> Result<Self> {
+        let mut handled_signals = forwarded_signal_set();
+
+        // SIGCHLD: Child status changes (always handled by init)
+        handled_signals.add(Signal::SIGCHLD);
 
         // Block these signals for synchronous handling
         handled_signals.thread_block()?;
@@ -88,109 +295,92 @@ impl SignalHandler {
         #[cfg(target_os = "linux")]
         {
             // Linux: Use SignalFd for safe synchronous signal handling
-            let signal_fd = SignalFd::new(
+            let signal_fd = AsyncFd::new(SignalFd::with_flags(
                 &handled_signals,
                 SfdFlags::SFD_CLOEXEC | SfdFlags::SFD_NONBLOCK,
-            )?;
+            )?)?;
             debug!("Signal handler initialized with SignalFd for Linux init semantics");
 
             Ok(SignalHandler {
                 handled_signals,
+                policy,
                 signal_fd,
             })
         }
         #[cfg(not(target_os = "linux"))]
         {
-            // Non-Linux: Use sigtimedwait for proper init system semantics
-            debug!("Signal handler initialized with sigtimedwait for init system semantics");
+            // Non-Linux: Use sigwait for proper init system semantics
+            debug!("Signal handler initialized with sigwait for init system semantics");
 
-            Ok(SignalHandler { handled_signals })
+            Ok(SignalHandler {
+                handled_signals,
+                policy,
+                pending_wait: None,
+            })
         }
     }
 
-    /// Waits for a signal with timeout using proper init system semantics.
+    /// Waits for the next signal using proper init system semantics.
     ///
-    /// This function provides synchronous, deterministic signal handling that
-    /// maintains init system guarantees for signal ordering and delivery.
+    /// Signals are read in delivery order and none is lost if the returned
+    /// future is dropped, so this is safe to use in `select!`.
     ///
-    /// # Arguments
-    /// * `timeout_duration` - The maximum time to wait for a signal
-    pub async fn wait_for_signal(&mut self, timeout_duration: Duration) -> Result<Option<Signal>> {
-        // Use spawn_blocking to maintain init semantics while being async-compatible
-        let signals = self.handled_signals;
+    /// # Returns
+    /// * `Result<i32>` - The signal number, which may be a real-time signal
+    pub async fn next_signal(&mut self) -> Result<i32> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx))
+            .await
+            .ok_or_else(|| eyre!("Signal stream ended"))?
+    }
+}
 
+impl Stream for SignalHandler {
+    type Item = Result<i32>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         #[cfg(target_os = "linux")]
         {
-            // Linux: Use SignalFd with blocking read in spawn_blocking
-            let mut signal_fd = std::mem::replace(
-                &mut self.signal_fd,
-                SignalFd::new(&signals, SfdFlags::SFD_CLOEXEC | SfdFlags::SFD_NONBLOCK)?,
-            );
-
-            let result = tokio::time::timeout(
-                timeout_duration,
-                tokio::task::spawn_blocking(move || -> Result<Signal> {
-                    loop {
-                        match signal_fd.read_signal() {
-                            Ok(Some(signal_info)) => {
-                                let signal = Signal::try_from(signal_info.ssi_signo as i32)?;
-                                debug!("Received signal: {:?} (init semantics)", signal);
-                                return Ok(signal);
-                            }
-                            Ok(None) => {
-                                // Would block - continue reading
-                                std::thread::sleep(Duration::from_millis(10));
-                                continue;
-                            }
-                            Err(nix::errno::Errno::EAGAIN) => {
-                                // Would block - continue reading
-                                std::thread::sleep(Duration::from_millis(10));
-                                continue;
-                            }
-                            Err(e) => {
-                                return Err(e.into());
-                            }
-                        }
+            // Linux: Wait for the reactor to report the SignalFd readable, then
+            // drain it; readiness is only cleared once a read would block.
+            loop {
+                let mut guard = match self.signal_fd.poll_read_ready(cx) {
+                    Poll::Ready(Ok(guard)) => guard,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                    Poll::Pending => return Poll::Pending,
+                };
+                match guard.get_inner().read_signal() {
+                    Ok(Some(signal_info)) => {
+                        let signal = signal_info.ssi_signo as i32;
+                        debug!("Received signal: {} (init semantics)", signal_name(signal));
+                        return Poll::Ready(Some(Ok(signal)));
                     }
-                }),
-            )
-            .await;
-
-            // Restore signal_fd
-            self.signal_fd = signal_fd;
-
-            match result {
-                Ok(Ok(signal)) => Ok(Some(signal)),
-                Ok(Err(e)) => Err(e),
-                Err(_) => {
-                    debug!("Signal wait timed out after {:?}", timeout_duration);
-                    Ok(None)
+                    Ok(None) | Err(nix::errno::Errno::EAGAIN) => guard.clear_ready(),
+                    Err(e) => return Poll::Ready(Some(Err(e.into()))),
                 }
             }
         }
         #[cfg(not(target_os = "linux"))]
         {
-            // Non-Linux: Use sigwait with timeout for proper init system semantics
-            let task = tokio::task::spawn_blocking(move || -> Result<Signal> {
-                // Use sigwait for synchronous signal waiting
-                match signals.wait() {
-                    Ok(signal) => {
-                        debug!("Received signal: {:?} (init semantics)", signal);
-                        Ok(signal)
-                    }
-                    Err(e) => Err(e.into()),
-                }
-            });
-
-            match tokio::time::timeout(timeout_duration, task).await {
-                Ok(Ok(Ok(signal))) => Ok(Some(signal)),
-                Ok(Ok(Err(e))) => Err(e),
-                Ok(Err(join_err)) => Err(join_err.into()),
-                Err(_) => {
-                    debug!("Signal wait timed out after {:?}", timeout_duration);
-                    Ok(None)
+            // Non-Linux: Keep a single sigwait in flight until it completes
+            let this = self.get_mut();
+            let signals = this.handled_signals;
+            let task = this
+                .pending_wait
+                .get_or_insert_with(|| tokio::task::spawn_blocking(move || signals.wait()));
+
+            let result = match std::future::Future::poll(Pin::new(task), cx) {
+                Poll::Ready(result) => result,
+                Poll::Pending => return Poll::Pending,
+            };
+            this.pending_wait = None;
+            Poll::Ready(Some(match result {
+                Ok(Ok(signal)) => {
+                    debug!("Received signal: {:?} (init semantics)", signal);
+                    Ok(signal as i32)
                 }
-            }
+                Ok(Err(e)) => Err(e.into()),
+                Err(join_err) => Err(join_err.into()),
+            }))
         }
     }
 }
@@ -199,93 +389,134 @@ impl SignalHandler {
     /// Processes a specific signal according to init system semantics
     pub async fn process_signal(
         &self,
-        signal: Signal,
-        process_manager: &mut ProcessManager,
+        signal: i32,
+        supervisor: &mut Supervisor,
         graceful_timeout_secs: u64,
     ) -> Result<SignalAction> {
+        let Ok(signal) = Signal::try_from(signal) else {
+            // Real-time signals carry application-defined meaning; only the policy changes them
+            debug!("forwarding real-time signal {} to child processes", signal_name(signal));
+            self.forward(signal, supervisor);
+            return Ok(SignalAction::Continue);
+        };
+
         match signal {
             Signal::SIGCHLD => {
                 // Reap zombie processes asynchronously - this is always handled by init
                 debug!("received SIGCHLD, reaping zombie processes");
                 Ok(SignalAction::ReapZombies)
             }
-            Signal::SIGTERM | Signal::SIGINT | Signal::SIGQUIT => {
-                // Scenario B: Signal forwarding with graceful shutdown and timeout
+            _ if self.policy.restart_signals.contains(&signal) => {
+                info!("received restart signal {:?}, restarting services", signal);
+                Ok(SignalAction::Restart(signal))
+            }
+            Signal::SIGTERM | Signal::SIGINT | Signal::SIGQUIT | Signal::SIGPWR => {
+                // Scenario B: Signal forwarding with graceful shutdown and timeout;
+                // a rewrite to none only keeps the signal itself from being forwarded
                 info!(
                     "received termination signal {:?}, initiating graceful shutdown",
                     signal
                 );
-                self.handle_termination_signal(signal, process_manager, graceful_timeout_secs)
+                self.handle_termination_signal(signal, supervisor, graceful_timeout_secs)
                     .await?;
                 Ok(SignalAction::Exit)
             }
+            _ if self.policy.rewrite(signal as i32).is_none() => {
+                info!("ignoring signal {:?}, dropped by rewrite policy", signal);
+                Ok(SignalAction::Continue)
+            }
             Signal::SIGUSR1 | Signal::SIGUSR2 | Signal::SIGHUP => {
-                // These signals should be forwarded to the child process only
-                info!("forwarding signal {:?} to child process", signal);
-                if let Err(e) = process_manager.forward_signal(signal) {
-                    warn!("failed to forward signal {:?} to child: {}", signal, e);
+                // These signals should be forwarded to the child processes only
+                info!("forwarding signal {:?} to child processes", signal);
+                self.forward(signal as i32, supervisor);
+                Ok(SignalAction::Continue)
+            }
+            Signal::SIGTSTP => {
+                info!("forwarding signal {:?} to child processes", signal);
+                self.forward(signal as i32, supervisor);
+                // With job control the services stop first, then scinit follows
+                // so the shell sees the job as stopped. That freezes all of
+                // scinit: restarts, probes and watchdogs wait until a SIGCONT
+                // resumes it, which is only forwarded once scinit runs again.
+                if self.policy.job_control && nix::unistd::getpid().as_raw() != 1 {
+                    nix::sys::signal::raise(Signal::SIGSTOP)?;
                 }
                 Ok(SignalAction::Continue)
             }
             _ => {
-                // Any other signals we somehow receive should be forwarded
-                debug!("forwarding unexpected signal {:?} to child process", signal);
-                if let Err(e) = process_manager.forward_signal(signal) {
-                    warn!("failed to forward signal {:?} to child: {}", signal, e);
-                }
+                // SIGWINCH, SIGCONT, SIGALRM, SIGURG and the rest are frequent
+                // or informational, so they are forwarded without ceremony
+                debug!("forwarding signal {:?} to child processes", signal);
+                self.forward(signal as i32, supervisor);
                 Ok(SignalAction::Continue)
             }
         }
     }
 
+    /// Forwards a received signal to the services after applying the rewrite policy
+    fn forward(&self, signal: i32, supervisor: &Supervisor) {
+        match self.policy.rewrite(signal) {
+            Some(rewritten) => {
+                if rewritten != signal {
+                    debug!("rewriting signal {} to {}", signal_name(signal), signal_name(rewritten));
+                }
+                supervisor.forward_signal(rewritten, self.policy.target(rewritten));
+            }
+            None => debug!("not forwarding signal {}, dropped by rewrite policy", signal_name(signal)),
+        }
+    }
+
     /// Handles termination signals with proper timeout and escalation (Scenario B)
     async fn handle_termination_signal(
         &self,
         signal: Signal,
-        process_manager: &mut ProcessManager,
+        supervisor: &mut Supervisor,
         graceful_timeout_secs: u64,
     ) -> Result<()> {
-        info!(
-            "Termination signal {:?} received, forwarding to child process",
-            signal
-        );
-
-        // Forward the signal to child process
-        if let Err(e) = process_manager.forward_signal(signal) {
-            warn!("Failed to forward signal {:?} to child: {}", signal, e);
-        }
-
         match signal {
-            Signal::SIGTERM => {
-                // SIGTERM gets graceful shutdown with timeout
+            Signal::SIGINT | Signal::SIGQUIT if self.policy.rewrite(signal as i32).is_some() => {
                 info!(
-                    "Waiting for child process to exit gracefully (timeout: {}s)",
-                    graceful_timeout_secs
+                    "Termination signal {:?} received, forwarding to child processes",
+                    signal
                 );
+                self.forward(signal as i32, supervisor);
 
-                if (process_manager.graceful_shutdown().await).is_err() {
-                    warn!("Graceful shutdown timed out, child process may have been force-killed");
-                }
-            }
-            Signal::SIGINT | Signal::SIGQUIT => {
                 // SIGINT/SIGQUIT get shorter timeout or immediate cleanup
                 info!("Waiting for child process to exit (signal: {:?})", signal);
 
                 // Wait a bit for child to exit, but don't use full graceful timeout
                 tokio::time::sleep(Duration::from_secs(2)).await;
 
-                // Force kill if still running
-                if process_manager.is_running() {
-                    warn!(
-                        "Child process didn't exit after {:?}, forcing termination",
-                        signal
-                    );
-                    if let Err(e) = process_manager.force_kill().await {
-                        error!("Failed to force kill child process: {}", e);
+                // Force kill any that are still running
+                for process_manager in supervisor.managers_mut() {
+                    process_manager.stop();
+                    if process_manager.is_running() {
+                        warn!(
+                            "Child process didn't exit after {:?}, forcing termination",
+                            signal
+                        );
+                        if let Err(e) = process_manager.force_kill().await {
+                            error!("Failed to force kill child process: {}", e);
+                        }
                     }
                 }
             }
-            _ => unreachable!(),
+            _ => {
+                // SIGTERM (and SIGPWR, which container runtimes like LXC use
+                // to request a halt) gets graceful shutdown with timeout; every service
+                // receives its stop signal, which defaults to the rewritten SIGTERM.
+                // So does SIGINT or SIGQUIT when the rewrite policy drops it.
+                info!(
+                    "Termination signal {:?} received, stopping child processes",
+                    signal
+                );
+                info!(
+                    "Waiting for child processes to exit gracefully (timeout: {}s)",
+                    graceful_timeout_secs
+                );
+
+                supervisor.shutdown_all().await;
+            }
         }
 
         info!("scinit exiting due to termination signal {:?}", signal);
@@ -302,4 +533,164 @@ pub enum SignalAction {
     ReapZombies,
     /// Exit the init system
     Exit,
+    /// Restart the services on request of the operator
+    Restart(Signal),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_parsing() {
+        assert_eq!(parse_signal("SIGTERM").unwrap(), Signal::SIGTERM);
+        assert_eq!(parse_signal("quit").unwrap(), Signal::SIGQUIT);
+        assert_eq!(parse_signal("10").unwrap(), Signal::SIGUSR1);
+        assert!(parse_signal("SIGNOPE").is_err());
+        assert!(parse_signal("999").is_err());
+    }
+
+    #[test]
+    fn test_signal_names() {
+        let (min, max) = realtime_signal_range();
+        assert_eq!(signal_name(15), "SIGTERM");
+        assert_eq!(signal_name(libc::SIGWINCH), "SIGWINCH");
+        assert_eq!(signal_name(libc::SIGPWR), "SIGPWR");
+        assert_eq!(signal_name(min), "SIGRTMIN");
+        assert_eq!(signal_name(min + 2), "SIGRTMIN+2");
+        assert_eq!(signal_name(max - 1), "SIGRTMAX-1");
+        assert_eq!(signal_name(max), "SIGRTMAX");
+    }
+
+    #[test]
+    fn test_forwarded_signal_set() {
+        let signals = forwarded_signal_set();
+        for signal in [Signal::SIGWINCH, Signal::SIGCONT, Signal::SIGTSTP, Signal::SIGPWR, Signal::SIGALRM, Signal::SIGURG] {
+            assert!(signals.contains(signal), "{:?} is not routed", signal);
+        }
+        for signal in UNROUTED_SIGNALS {
+            assert!(!signals.contains(signal), "{:?} is routed", signal);
+        }
+        let (min, max) = realtime_signal_range();
+        // SAFETY: sigismember only reads the initialized set
+        assert_eq!(unsafe { libc::sigismember(signals.as_ref(), min) }, 1);
+        assert_eq!(unsafe { libc::sigismember(signals.as_ref(), max) }, 1);
+    }
+
+    #[test]
+    fn test_signal_policy() {
+        let policy = SignalPolicy {
+            rewrites: [
+                parse_signal_rewrite("TERM=QUIT").unwrap(),
+                parse_signal_rewrite("SIGUSR2=none").unwrap(),
+            ]
+            .into_iter()
+            .collect(),
+            targets: [parse_signal_target("QUIT=child").unwrap()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(policy.rewrite(libc::SIGTERM), Some(libc::SIGQUIT));
+        assert_eq!(policy.rewrite(libc::SIGUSR2), None);
+        assert_eq!(policy.rewrite(libc::SIGHUP), Some(libc::SIGHUP));
+        assert_eq!(policy.target(libc::SIGQUIT), SignalTarget::Child);
+        assert_eq!(policy.target(libc::SIGHUP), SignalTarget::Group);
+
+        let (min, max) = realtime_signal_range();
+        assert_eq!(parse_signal_rewrite("SIGRTMIN+1=RTMAX").unwrap(), (min + 1, Some(max)));
+        assert_eq!(parse_signal_target("SIGRTMAX-2=child").unwrap(), (max - 2, SignalTarget::Child));
+        assert_eq!(parse_signal_number("SIGRTMIN").unwrap(), min);
+        assert_eq!(parse_signal_number(&min.to_string()).unwrap(), min);
+        assert!(parse_signal_number("SIGRTMIN+100").is_err());
+        assert!(parse_signal_number("RTMINX").is_err());
+
+        assert!(parse_signal_rewrite("TERM").is_err());
+        assert!(parse_signal_target("HUP=parent").is_err());
+    }
+
+    fn sleeping_supervisor() -> Supervisor {
+        use crate::port_manager::PortBindingConfig;
+        use crate::process_manager::ProcessConfig;
+        use crate::supervisor::{ServiceConfig, ServiceExitPolicy};
+
+        Supervisor::new(vec![ServiceConfig {
+            name: "main".to_string(),
+            process: ProcessConfig {
+                command: "sleep".to_string(),
+                args: vec!["10".to_string()],
+                graceful_shutdown_timeout: Duration::from_millis(500),
+                ..Default::default()
+            },
+            ports: PortBindingConfig::default(),
+            on_exit: ServiceExitPolicy::ExitAll,
+        }])
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dropped_termination_signals_still_shut_down() {
+        for signal in [Signal::SIGTERM, Signal::SIGQUIT] {
+            let policy = SignalPolicy {
+                rewrites: [(signal as i32, None)].into_iter().collect(),
+                ..Default::default()
+            };
+            let handler = SignalHandler::new(policy).unwrap();
+            let mut supervisor = sleeping_supervisor();
+            supervisor.spawn_all().await.unwrap();
+
+            let action = handler.process_signal(signal as i32, &mut supervisor, 1).await.unwrap();
+            assert_eq!(action, SignalAction::Exit);
+            assert!(!supervisor.primary().is_running(), "{:?} did not stop the service", signal);
+        }
+    }
+
+    /// State letter of a process from /proc, e.g. `T` when stopped
+    fn process_state(pid: nix::unistd::Pid) -> char {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+        stat.rsplit_once(") ").unwrap().1.chars().next().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tstp_stops_only_the_services_without_job_control() {
+        let handler = SignalHandler::new(SignalPolicy::default()).unwrap();
+        let mut supervisor = sleeping_supervisor();
+        supervisor.spawn_all().await.unwrap();
+        let pid = supervisor.primary().process_info().pid.unwrap();
+
+        // scinit keeps running, so this test gets to continue
+        assert_eq!(handler.process_signal(libc::SIGTSTP, &mut supervisor, 1).await.unwrap(), SignalAction::Continue);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(process_state(pid), 'T');
+
+        assert_eq!(handler.process_signal(libc::SIGCONT, &mut supervisor, 1).await.unwrap(), SignalAction::Continue);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_ne!(process_state(pid), 'T');
+        supervisor.shutdown_all().await;
+    }
+
+    #[tokio::test]
+    async fn test_realtime_signals_follow_the_policy() {
+        let (min, _) = realtime_signal_range();
+        let policy = SignalPolicy {
+            rewrites: [(min + 1, Some(libc::SIGUSR1)), (min + 2, None)].into_iter().collect(),
+            targets: [(libc::SIGUSR1, SignalTarget::Child)].into_iter().collect(),
+            ..Default::default()
+        };
+        let handler = SignalHandler::new(policy).unwrap();
+        let mut supervisor = sleeping_supervisor();
+        supervisor.spawn_all().await.unwrap();
+
+        // A dropped real-time signal would have killed the sleep
+        handler.process_signal(min + 2, &mut supervisor, 1).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(supervisor.primary().is_running());
+
+        handler.process_signal(min + 1, &mut supervisor, 1).await.unwrap();
+        let (_, event) = tokio::time::timeout(Duration::from_secs(5), supervisor.next_event()).await.unwrap();
+        use std::os::unix::process::ExitStatusExt;
+        match event.unwrap() {
+            crate::process_manager::ProcessEvent::Exited(status) => assert_eq!(status.signal(), Some(libc::SIGUSR1)),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
 }
@@ -0,0 +1,169 @@
+use super::Result;
+use eyre::eyre;
+use std::collections::HashMap;
+use std::ffi::{c_char, CString};
+use std::os::unix::io::RawFd;
+
+/// First file descriptor handed to the child (`SD_LISTEN_FDS_START`)
+pub const LISTEN_FDS_START: RawFd = 3;
+
+/// Prefix of the environment entry patched with the child's PID after fork
+const LISTEN_PID_PREFIX: &[u8] = b"LISTEN_PID=";
+
+extern "C" {
+    static mut environ: *const *const c_char;
+}
+
+/// Child-side setup for systemd-compatible socket activation
+///
+/// Libraries such as go-systemd, `listenfd` and `systemd.daemon` expect the
+/// listening sockets at fd 3 and up, `LISTEN_FDS`/`LISTEN_FDNAMES` describing
+/// them, and `LISTEN_PID` holding their own PID. The PID is only known after
+/// fork, so the whole environment block is built up front and installed as
+/// `environ` in the `pre_exec` hook, with the `LISTEN_PID` entry filled in
+/// place. Nothing in `apply_in_child` allocates.
+pub struct SocketActivation {
+    /// Listening sockets in the order they are passed to the child
+    fds: Vec<RawFd>,
+    /// Scratch space for the temporary duplicates made while moving fds
+    scratch: Vec<RawFd>,
+    /// Environment entries, owned so the pointers in `envp` stay valid
+    _entries: Vec<CString>,
+    /// Buffer holding the `LISTEN_PID=` entry, completed after fork
+    listen_pid: Vec<u8>,
+    /// NULL-terminated pointer array installed as the child's `environ`
+    envp: Vec<*const c_char>,
+}
+
+// The raw pointers only point into buffers owned by this struct, and they are
+// only dereferenced by exec in the forked child.
+unsafe impl Send for SocketActivation {}
+unsafe impl Sync for SocketActivation {}
+
+impl SocketActivation {
+    /// Prepares socket activation for the given sockets and child environment
+    ///
+    /// # Arguments
+    /// * `fds` - Listening sockets, in the order they should appear from fd 3
+    /// * `names` - Names for `LISTEN_FDNAMES`, one per socket
+    /// * `env` - The environment the child should otherwise receive
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The prepared activation state or an error
+    pub fn new(fds: Vec<RawFd>, names: Vec<String>, mut env: HashMap<String, String>) -> Result<Self> {
+        if fds.len() != names.len() {
+            return Err(eyre!("Socket activation needs one name per socket ({} fds, {} names)", fds.len(), names.len()));
+        }
+        if let Some(name) = names.iter().find(|name| name.contains(':') || name.len() > 255) {
+            return Err(eyre!("Invalid LISTEN_FDNAMES entry '{}': names can't contain ':' or exceed 255 bytes", name));
+        }
+
+        env.remove("LISTEN_PID");
+        env.insert("LISTEN_FDS".to_string(), fds.len().to_string());
+        env.insert("LISTEN_FDNAMES".to_string(), names.join(":"));
+
+        let entries = env
+            .into_iter()
+            .map(|(key, value)| CString::new(format!("{}={}", key, value)))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| eyre!("Invalid environment variable for socket activation: {}", e))?;
+
+        // Room for the longest possible PID plus the trailing NUL
+        let mut listen_pid = LISTEN_PID_PREFIX.to_vec();
+        listen_pid.resize(LISTEN_PID_PREFIX.len() + 21, 0);
+
+        let mut envp: Vec<*const c_char> = entries.iter().map(|entry| entry.as_ptr()).collect();
+        envp.push(listen_pid.as_ptr() as *const c_char);
+        envp.push(std::ptr::null());
+
+        Ok(Self {
+            scratch: vec![-1; fds.len()],
+            fds,
+            _entries: entries,
+            listen_pid,
+            envp,
+        })
+    }
+
+    /// File descriptor numbers a child receiving `count` sockets will see, in order
+    pub fn child_fds(count: usize) -> impl Iterator<Item = RawFd> {
+        LISTEN_FDS_START..LISTEN_FDS_START + count as RawFd
+    }
+
+    /// Moves the sockets into place and installs the environment
+    ///
+    /// Must only be called in the forked child before exec; it only uses
+    /// async-signal-safe system calls.
+    pub fn apply_in_child(&mut self) -> std::io::Result<()> {
+        let target_end = LISTEN_FDS_START + self.fds.len() as RawFd;
+
+        // Duplicate every socket above the target range first, so moving one
+        // socket into place can never clobber another that is still to be moved
+        for (tmp, &fd) in self.scratch.iter_mut().zip(&self.fds) {
+            *tmp = check(unsafe { libc::fcntl(fd, libc::F_DUPFD, target_end) })?;
+        }
+        for &fd in &self.fds {
+            unsafe { libc::close(fd) };
+        }
+
+        // dup2 clears FD_CLOEXEC on the new descriptor
+        for (target, &tmp) in (LISTEN_FDS_START..).zip(&self.scratch) {
+            check(unsafe { libc::dup2(tmp, target) })?;
+            unsafe { libc::close(tmp) };
+        }
+
+        self.write_listen_pid();
+        unsafe { environ = self.envp.as_ptr() };
+        Ok(())
+    }
+
+    /// Completes the `LISTEN_PID=` entry with the current PID
+    fn write_listen_pid(&mut self) {
+        let mut pid = unsafe { libc::getpid() } as u32;
+        let mut digits = [0u8; 20];
+        let mut len = 0;
+        loop {
+            digits[len] = b'0' + (pid % 10) as u8;
+            len += 1;
+            pid /= 10;
+            if pid == 0 {
+                break;
+            }
+        }
+
+        let start = LISTEN_PID_PREFIX.len();
+        for (i, digit) in digits[..len].iter().rev().enumerate() {
+            self.listen_pid[start + i] = *digit;
+        }
+        self.listen_pid[start + len] = 0;
+    }
+}
+
+/// Converts a libc return value into an io::Result
+fn check(ret: libc::c_int) -> std::io::Result<libc::c_int> {
+    if ret < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_invalid_names() {
+        let result = SocketActivation::new(vec![10], vec!["http:alt".to_string()], HashMap::new());
+        assert!(result.is_err());
+
+        let result = SocketActivation::new(vec![10, 11], vec!["http".to_string()], HashMap::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_child_fds_start_at_three() {
+        assert_eq!(SocketActivation::child_fds(2).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(SocketActivation::child_fds(0).count(), 0);
+    }
+}
@@ -0,0 +1,585 @@
+use super::Result;
+use crate::port_manager::{ListenerSpec, PortBindingConfig, PortManager};
+use crate::process_manager::{exit_code_from_status, handle_child_exit, ProcessConfig, ProcessEvent, ProcessManager, RestartMode, RestartOutcome, RestartReason};
+use crate::restart_policy::{RestartDecision, RestartPolicy};
+use crate::rlimits::RlimitSpec;
+use crate::signals::{signal_name, SignalTarget};
+use eyre::eyre;
+use nix::sys::signal::Signal;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::task::Poll;
+use tracing::{error, info, info_span, warn, Instrument, Span};
+
+/// Name of the service started from the command line's main command
+pub const PRIMARY_SERVICE: &str = "main";
+
+/// What happens when a service exits and its restart policy doesn't restart it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ServiceExitPolicy {
+    /// Stop every other service and exit with the service's exit code
+    ExitAll,
+    /// Restart the service anyway, still subject to its backoff and restart budget
+    Restart,
+    /// Leave the service stopped; scinit exits once no service is left
+    Ignore,
+}
+
+/// An additional service, given on the command line as `NAME=COMMAND [ARGS...]`
+/// or as a `[[service]]` table in the configuration file
+///
+/// On the command line, the command line is split on whitespace and only
+/// the name, command and arguments can be set. Settings left unset fall
+/// back to those of the main command, except the restart mode, which
+/// defaults to stop-start because a sidecar usually has no readiness signal.
+/// Probes and the notify socket only apply to the main command. The restart
+/// backoff and budget, restart delay, graceful shutdown timeout and the
+/// `env_clear`/`env_allow` filtering are always shared with it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ServiceSpec {
+    /// Service name used in logs
+    pub name: String,
+    /// The command to execute
+    pub command: String,
+    /// Arguments for the command
+    pub args: Vec<String>,
+    /// Working directory of the service
+    pub working_directory: Option<PathBuf>,
+    /// Extra environment variables for the service
+    pub environment: HashMap<String, String>,
+    /// Ports owned by the service
+    pub ports: Vec<u16>,
+    /// Additional listeners owned by the service
+    pub listeners: Vec<ListenerSpec>,
+    /// Restart policy, if different from the main command's
+    pub restart: Option<RestartPolicy>,
+    /// How the service is replaced on restart (default: stop-start)
+    pub restart_mode: Option<RestartMode>,
+    /// User the service runs as, if different from the main command's
+    pub user: Option<String>,
+    /// Primary group the service runs with, if different from the main command's
+    pub group: Option<String>,
+    /// Resource limits, replacing the main command's
+    pub rlimits: Option<Vec<RlimitSpec>>,
+    /// Signal that stops the service gracefully, if different from the main command's
+    pub stop_signal: Option<Signal>,
+    /// Dotenv-style files of environment variables, replacing the main command's
+    pub env_files: Option<Vec<PathBuf>>,
+    /// Exit policy, if different from the global one
+    pub on_exit: Option<ServiceExitPolicy>,
+}
+
+impl fmt::Display for ServiceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.command)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ServiceSpec {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, command_line) = s
+            .split_once('=')
+            .ok_or_else(|| eyre!("Invalid service '{}': expected NAME=COMMAND", s))?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(eyre!("Invalid service name '{}': use letters, digits, '-' and '_'", name));
+        }
+
+        let mut words = command_line.split_whitespace().map(str::to_string);
+        let command = words
+            .next()
+            .ok_or_else(|| eyre!("Invalid service '{}': missing command", s))?;
+        Ok(ServiceSpec {
+            name: name.to_string(),
+            command,
+            args: words.collect(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Configuration for one supervised service
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    /// Service name used in logs
+    pub name: String,
+    /// Process configuration, including the service's restart policy
+    pub process: ProcessConfig,
+    /// Ports and listeners owned by the service
+    pub ports: PortBindingConfig,
+    /// What happens when the service stops for good
+    pub on_exit: ServiceExitPolicy,
+}
+
+/// A supervised service and its process manager
+struct Service {
+    name: String,
+    manager: ProcessManager,
+    on_exit: ServiceExitPolicy,
+    /// Span carrying the service name into the process manager's logs
+    span: Span,
+}
+
+/// Pending `next_event` of one service, tagged with the service's index
+type ServiceEvent<'a> = Pin<Box<dyn Future<Output = (usize, Result<ProcessEvent>)> + Send + 'a>>;
+
+/// What the main loop should do after the supervisor handled an event
+#[derive(Debug, Clone, PartialEq)]
+pub enum SupervisorAction {
+    /// Keep running
+    Continue,
+    /// Every service has been stopped; exit with the given child exit code
+    Exit {
+        /// Exit code derived from the child's status
+        code: i32,
+        /// Whether the child was killed by the OOM killer
+        oom_killed: bool,
+    },
+    /// A service ran out of restarts; every service has been stopped
+    Exhausted,
+}
+
+/// Supervises a set of named services, each in its own process group
+///
+/// The first service is the primary one: it receives the terminal, is
+/// restarted on file changes and provides scinit's exit code on signals.
+pub struct Supervisor {
+    services: Vec<Service>,
+}
+
+impl Supervisor {
+    /// Creates a supervisor for the given services
+    ///
+    /// # Arguments
+    /// * `configs` - Service configurations, primary service first
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The supervisor, or an error for an empty or ambiguous set
+    pub fn new(configs: Vec<ServiceConfig>) -> Result<Self> {
+        if configs.is_empty() {
+            return Err(eyre!("No services to supervise"));
+        }
+        for (i, config) in configs.iter().enumerate() {
+            if configs[..i].iter().any(|other| other.name == config.name) {
+                return Err(eyre!("Duplicate service name '{}'", config.name));
+            }
+        }
+
+        let services = configs
+            .into_iter()
+            .map(|config| Service {
+                span: info_span!("service", name = %config.name),
+                manager: ProcessManager::new(config.process, PortManager::new(config.ports)),
+                name: config.name,
+                on_exit: config.on_exit,
+            })
+            .collect();
+        Ok(Self { services })
+    }
+
+    /// Spawns every service, in order
+    pub async fn spawn_all(&mut self) -> Result<()> {
+        for service in &mut self.services {
+            info!("Starting service {}", service.name);
+            service
+                .manager
+                .spawn_process()
+                .instrument(service.span.clone())
+                .await
+                .map_err(|e| eyre!("Failed to start service '{}': {}", service.name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Gets the process manager of the primary service
+    pub fn primary(&self) -> &ProcessManager {
+        &self.services[0].manager
+    }
+
+    /// Gets the process manager of the primary service, mutably
+    pub fn primary_mut(&mut self) -> &mut ProcessManager {
+        &mut self.services[0].manager
+    }
+
+    /// Iterates over the process managers of all services
+    pub fn managers_mut(&mut self) -> impl Iterator<Item = &mut ProcessManager> {
+        self.services.iter_mut().map(|service| &mut service.manager)
+    }
+
+    /// Gets the name of the service at `index`
+    pub fn name(&self, index: usize) -> &str {
+        &self.services[index].name
+    }
+
+    /// Checks if any service has a child process to wait for
+    pub fn has_children(&self) -> bool {
+        self.services.iter().any(|service| service.manager.has_child())
+    }
+
+    /// Gets the earliest time a scheduled restart is due
+    pub fn pending_restart(&self) -> Option<tokio::time::Instant> {
+        self.services
+            .iter()
+            .filter_map(|service| service.manager.pending_restart())
+            .min()
+    }
+
+    /// Waits for the next event from any service
+    ///
+    /// Never resolves if no service has a child. This method is cancel-safe.
+    ///
+    /// # Returns
+    /// * `(usize, Result<ProcessEvent>)` - Index of the service and its event
+    pub async fn next_event(&mut self) -> (usize, Result<ProcessEvent>) {
+        let mut events: Vec<ServiceEvent<'_>> = self
+            .services
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, service)| service.manager.has_child())
+            .map(|(index, service)| {
+                let span = service.span.clone();
+                let manager = &mut service.manager;
+                Box::pin(async move { (index, manager.next_event().instrument(span).await) }) as Pin<Box<_>>
+            })
+            .collect();
+
+        std::future::poll_fn(|cx| {
+            for event in events.iter_mut() {
+                if let Poll::Ready(result) = event.as_mut().poll(cx) {
+                    return Poll::Ready(result);
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Handles a service's process exiting on its own
+    ///
+    /// The service's restart policy is consulted first; if it doesn't restart
+    /// the process, the service's exit policy applies.
+    ///
+    /// # Arguments
+    /// * `index` - Index of the service
+    /// * `status` - Exit status of its process
+    pub async fn handle_exit(&mut self, index: usize, status: ExitStatus) -> Result<SupervisorAction> {
+        let decision = self.services[index].manager.schedule_restart(status);
+        self.apply_decision(index, decision, Some(status)).await
+    }
+
+    /// Handles a service failing its liveness probe or watchdog
+    ///
+    /// # Arguments
+    /// * `index` - Index of the service
+    pub async fn handle_unhealthy(&mut self, index: usize) -> Result<SupervisorAction> {
+        let service = &mut self.services[index];
+        let decision = service.manager.handle_unhealthy().instrument(service.span.clone()).await?;
+        let status = service.manager.process_info().exit_status;
+        self.apply_decision(index, decision, status).await
+    }
+
+    /// Restarts every service whose scheduled restart is due
+    pub async fn restart_due(&mut self) -> Result<SupervisorAction> {
+        let now = tokio::time::Instant::now();
+        for index in 0..self.services.len() {
+            let service = &mut self.services[index];
+            if service.manager.pending_restart().is_none_or(|at| at > now) {
+                continue;
+            }
+
+            let reason = service.manager.pending_restart_reason();
+            let restarted = service
+                .manager
+                .restart_process_with_reason(reason)
+                .instrument(service.span.clone())
+                .await;
+            match restarted {
+                Ok(RestartOutcome::Exhausted) => {
+                    error!("Service {} failed, stopping all services", self.services[index].name);
+                    self.shutdown_all().await;
+                    return Ok(SupervisorAction::Exhausted);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    if let Some(action) = self.restart_failed(index, e).await? {
+                        return Ok(action);
+                    }
+                }
+            }
+        }
+        Ok(SupervisorAction::Continue)
+    }
+
+    /// Restarts every running service on request of the operator
+    ///
+    /// # Arguments
+    /// * `signal` - The signal that requested the restart, logged as the reason
+    /// * `mode` - Whether to stop each old process first or overlap the two generations
+    pub async fn restart_all(&mut self, signal: Signal, mode: RestartMode) -> Result<SupervisorAction> {
+        for index in 0..self.services.len() {
+            let service = &mut self.services[index];
+            if !service.manager.has_child() {
+                continue;
+            }
+
+            let restarted = service
+                .manager
+                .restart_process_with_mode(RestartReason::Signal(signal), mode)
+                .instrument(service.span.clone())
+                .await;
+            if let Err(e) = restarted {
+                if let Some(action) = self.restart_failed(index, e).await? {
+                    return Ok(action);
+                }
+            }
+        }
+        Ok(SupervisorAction::Continue)
+    }
+
+    /// Handles a restart whose spawn failed
+    ///
+    /// A failed spawn counts as another crash against the restart budget.
+    ///
+    /// # Returns
+    /// * `Result<Option<SupervisorAction>>` - None if a retry was scheduled, otherwise the action to stop with
+    async fn restart_failed(&mut self, index: usize, e: eyre::Report) -> Result<Option<SupervisorAction>> {
+        let service = &mut self.services[index];
+        error!("Restart of service {} failed: {}", service.name, e);
+        let last_status = service.manager.process_info().exit_status;
+        match last_status.map(|status| service.manager.schedule_restart(status)) {
+            Some(RestartDecision::Restart(_)) => Ok(None),
+            Some(RestartDecision::Exhausted) => {
+                self.shutdown_all().await;
+                Ok(Some(SupervisorAction::Exhausted))
+            }
+            Some(RestartDecision::Exit) | None => {
+                self.shutdown_all().await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Acts on the restart policy's decision for a stopped service
+    async fn apply_decision(
+        &mut self,
+        index: usize,
+        decision: RestartDecision,
+        status: Option<ExitStatus>,
+    ) -> Result<SupervisorAction> {
+        let oom_killed = self.services[index].manager.process_info().oom_killed;
+        let code = match decision {
+            RestartDecision::Restart(_) => return Ok(SupervisorAction::Continue),
+            RestartDecision::Exhausted => {
+                if let Some(status) = status {
+                    handle_child_exit(status, oom_killed).await?;
+                }
+                error!("Service {} failed, stopping all services", self.services[index].name);
+                self.shutdown_all().await;
+                return Ok(SupervisorAction::Exhausted);
+            }
+            RestartDecision::Exit => status.map(exit_code_from_status).unwrap_or(1),
+        };
+
+        let service = &mut self.services[index];
+        match service.on_exit {
+            ServiceExitPolicy::ExitAll => {
+                if let Some(status) = status {
+                    handle_child_exit(status, oom_killed).await?;
+                }
+                if self.services.len() > 1 {
+                    info!("Service {} exited with code {}, stopping all services", self.services[index].name, code);
+                }
+                self.shutdown_all().await;
+                Ok(SupervisorAction::Exit { code, oom_killed })
+            }
+            ServiceExitPolicy::Restart => match service.manager.schedule_forced_restart() {
+                RestartDecision::Exhausted => {
+                    self.shutdown_all().await;
+                    Ok(SupervisorAction::Exhausted)
+                }
+                _ => Ok(SupervisorAction::Continue),
+            },
+            ServiceExitPolicy::Ignore => {
+                info!("Service {} exited with code {}, leaving it stopped", service.name, code);
+                let active = self
+                    .services
+                    .iter()
+                    .any(|service| service.manager.has_child() || service.manager.pending_restart().is_some());
+                if active {
+                    Ok(SupervisorAction::Continue)
+                } else {
+                    info!("No services left running");
+                    Ok(SupervisorAction::Exit { code, oom_killed })
+                }
+            }
+        }
+    }
+
+    /// Sends a signal given by number, which may be a real-time signal, to every service
+    ///
+    /// Each service receives it in its process group or main process only, as `target` says.
+    pub fn forward_signal(&self, signal: i32, target: SignalTarget) {
+        for service in &self.services {
+            if !service.manager.has_child() {
+                continue;
+            }
+            if let Err(e) = service.manager.send_raw_signal(signal, target) {
+                warn!("Failed to forward signal {} to service {}: {}", signal_name(signal), service.name, e);
+            }
+        }
+    }
+
+    /// Gracefully stops every service and cancels pending restarts
+    ///
+    /// All services get their stop signal up front, so their shutdown
+    /// timeouts run concurrently rather than one after the other.
+    pub async fn shutdown_all(&mut self) {
+        for service in &mut self.services {
+            service.manager.stop();
+            if service.manager.has_child() {
+                if let Err(e) = service.manager.send_stop_signal() {
+                    warn!("Failed to send {:?} to service {}: {}", service.manager.stop_signal(), service.name, e);
+                }
+            }
+        }
+
+        for service in &mut self.services {
+            if let Err(e) = service.manager.graceful_shutdown().instrument(service.span.clone()).await {
+                warn!("Failed to stop service {}: {}", service.name, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    fn service(name: &str, script: &str, on_exit: ServiceExitPolicy) -> ServiceConfig {
+        ServiceConfig {
+            name: name.to_string(),
+            process: ProcessConfig {
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                graceful_shutdown_timeout: Duration::from_millis(500),
+                ..Default::default()
+            },
+            ports: PortBindingConfig::default(),
+            on_exit,
+        }
+    }
+
+    async fn next_exit(supervisor: &mut Supervisor) -> (usize, ExitStatus) {
+        loop {
+            match timeout(Duration::from_secs(5), supervisor.next_event()).await.unwrap() {
+                (index, Ok(ProcessEvent::Exited(status))) => return (index, status),
+                (_, Ok(_)) => continue,
+                (_, Err(e)) => panic!("unexpected error {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_service_spec_parsing() {
+        assert_eq!(
+            "logs=vector --config /etc/vector.toml".parse::<ServiceSpec>().unwrap(),
+            ServiceSpec {
+                name: "logs".to_string(),
+                command: "vector".to_string(),
+                args: vec!["--config".to_string(), "/etc/vector.toml".to_string()],
+                ..Default::default()
+            }
+        );
+        assert!("logs=".parse::<ServiceSpec>().is_err());
+        assert!("vector".parse::<ServiceSpec>().is_err());
+        assert!("bad name=true".parse::<ServiceSpec>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exit_all_stops_other_services() {
+        let mut supervisor = Supervisor::new(vec![
+            service("main", "exec sleep 10", ServiceExitPolicy::ExitAll),
+            service("sidecar", "sleep 0.2; exit 4", ServiceExitPolicy::ExitAll),
+        ])
+        .unwrap();
+        supervisor.spawn_all().await.unwrap();
+
+        let (index, status) = next_exit(&mut supervisor).await;
+        assert_eq!(supervisor.name(index), "sidecar");
+        assert_eq!(supervisor.handle_exit(index, status).await.unwrap(), SupervisorAction::Exit { code: 4, oom_killed: false });
+        assert!(!supervisor.has_children());
+        assert!(!supervisor.primary().is_running());
+    }
+
+    #[tokio::test]
+    async fn test_ignore_and_restart_policies() {
+        let mut flaky = service("flaky", "sleep 0.1; exit 1", ServiceExitPolicy::Restart);
+        flaky.process.restart_policy = crate::restart_policy::RestartPolicyConfig {
+            initial_backoff: Duration::from_millis(10),
+            jitter: 0.0,
+            max_restarts: 1,
+            ..Default::default()
+        };
+        let mut supervisor = Supervisor::new(vec![
+            service("main", "sleep 0.6; exit 0", ServiceExitPolicy::Ignore),
+            service("oneshot", "exit 0", ServiceExitPolicy::Ignore),
+            flaky,
+        ])
+        .unwrap();
+        supervisor.spawn_all().await.unwrap();
+
+        // The one-shot service is left stopped while the others keep running
+        let (index, status) = next_exit(&mut supervisor).await;
+        assert_eq!(supervisor.name(index), "oneshot");
+        assert_eq!(supervisor.handle_exit(index, status).await.unwrap(), SupervisorAction::Continue);
+
+        // The flaky service is restarted despite the "never" restart policy...
+        let (index, status) = next_exit(&mut supervisor).await;
+        assert_eq!(supervisor.name(index), "flaky");
+        assert_eq!(supervisor.handle_exit(index, status).await.unwrap(), SupervisorAction::Continue);
+        assert!(supervisor.pending_restart().is_some());
+        tokio::time::sleep_until(supervisor.pending_restart().unwrap()).await;
+        assert_eq!(supervisor.restart_due().await.unwrap(), SupervisorAction::Continue);
+
+        // ...until its restart budget runs out, which stops everything
+        let (index, status) = next_exit(&mut supervisor).await;
+        assert_eq!(supervisor.name(index), "flaky");
+        assert_eq!(supervisor.handle_exit(index, status).await.unwrap(), SupervisorAction::Exhausted);
+        assert!(!supervisor.has_children());
+    }
+
+    #[tokio::test]
+    async fn test_restart_all_replaces_running_services() {
+        let mut supervisor = Supervisor::new(vec![
+            service("main", "exec sleep 10", ServiceExitPolicy::ExitAll),
+            service("oneshot", "exit 0", ServiceExitPolicy::Ignore),
+        ])
+        .unwrap();
+        supervisor.spawn_all().await.unwrap();
+        let (index, status) = next_exit(&mut supervisor).await;
+        assert_eq!(supervisor.handle_exit(index, status).await.unwrap(), SupervisorAction::Continue);
+        let old_pid = supervisor.primary().process_info().pid;
+
+        for mode in [RestartMode::StopStart, RestartMode::Overlap] {
+            assert_eq!(supervisor.restart_all(Signal::SIGHUP, mode).await.unwrap(), SupervisorAction::Continue);
+        }
+        assert!(supervisor.primary().is_running());
+        assert_ne!(supervisor.primary().process_info().pid, old_pid);
+        // Stopped services stay stopped
+        assert!(!supervisor.managers_mut().nth(1).unwrap().has_child());
+        supervisor.shutdown_all().await;
+    }
+}
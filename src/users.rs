@@ -0,0 +1,173 @@
+use super::Result;
+use eyre::eyre;
+use nix::unistd::{getgrouplist, setgid, setgroups, setuid, Gid, Group, Uid, User};
+use std::ffi::CString;
+use std::path::PathBuf;
+
+/// Identity the managed process runs as after dropping privileges
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    /// User ID
+    pub uid: Uid,
+    /// Primary group ID
+    pub gid: Gid,
+    /// Supplementary groups, including the primary group
+    pub groups: Vec<Gid>,
+    /// User name, if the user is in `/etc/passwd`
+    pub name: Option<String>,
+    /// Home directory, if the user is in `/etc/passwd`
+    pub home: Option<PathBuf>,
+}
+
+impl Credentials {
+    /// Resolves `--user` and `--group` into the identity to switch to
+    ///
+    /// A user known to `/etc/passwd` brings its primary group and its
+    /// supplementary groups from `/etc/group`; an explicit group replaces the
+    /// primary group, which the process then only keeps if `/etc/group` lists
+    /// the user as a member of it. A numeric UID without a passwd entry gets the group of
+    /// the same number unless one is given. With only a group, the UID is
+    /// kept and only the groups change.
+    ///
+    /// # Arguments
+    /// * `user` - User name or numeric UID
+    /// * `group` - Group name or numeric GID
+    ///
+    /// # Returns
+    /// * `Result<Option<Self>>` - The identity, None if neither is given, or a lookup error
+    pub fn resolve(user: Option<&str>, group: Option<&str>) -> Result<Option<Self>> {
+        if user.is_none() && group.is_none() {
+            return Ok(None);
+        }
+
+        let gid = group.map(resolve_gid).transpose()?;
+        let entry = match user {
+            Some(user) => {
+                let uid = resolve_uid(user)?;
+                User::from_uid(uid).map_err(|e| eyre!("Failed to look up user '{}': {}", user, e))?.ok_or(uid)
+            }
+            None => Err(Uid::current()),
+        };
+
+        let credentials = match entry {
+            Ok(entry) => {
+                let gid = gid.unwrap_or(entry.gid);
+                let name = CString::new(entry.name.as_str())?;
+                let groups = getgrouplist(&name, gid)
+                    .map_err(|e| eyre!("Failed to look up groups of user '{}': {}", entry.name, e))?;
+                Credentials {
+                    uid: entry.uid,
+                    gid,
+                    groups,
+                    name: Some(entry.name),
+                    home: Some(entry.dir),
+                }
+            }
+            Err(uid) => {
+                let gid = gid.unwrap_or_else(|| Gid::from_raw(uid.as_raw()));
+                Credentials {
+                    uid,
+                    gid,
+                    groups: vec![gid],
+                    name: None,
+                    home: None,
+                }
+            }
+        };
+        Ok(Some(credentials))
+    }
+
+    /// Switches the calling process to this identity
+    ///
+    /// Meant for the child between fork and exec: groups are set first,
+    /// since changing the UID gives up the right to change them.
+    pub fn apply(&self) -> nix::Result<()> {
+        setgroups(&self.groups)?;
+        setgid(self.gid)?;
+        setuid(self.uid)
+    }
+}
+
+/// Resolves a user name or numeric UID
+///
+/// # Arguments
+/// * `user` - User name from `/etc/passwd` or a numeric UID
+///
+/// # Returns
+/// * `Result<Uid>` - The resolved UID or an error if the name is unknown
+pub fn resolve_uid(user: &str) -> Result<Uid> {
+    if let Ok(uid) = user.parse::<u32>() {
+        return Ok(Uid::from_raw(uid));
+    }
+
+    User::from_name(user)
+        .map_err(|e| eyre!("Failed to look up user '{}': {}", user, e))?
+        .map(|user| user.uid)
+        .ok_or_else(|| eyre!("Unknown user '{}'", user))
+}
+
+/// Resolves a group name or numeric GID
+///
+/// # Arguments
+/// * `group` - Group name from `/etc/group` or a numeric GID
+///
+/// # Returns
+/// * `Result<Gid>` - The resolved GID or an error if the name is unknown
+pub fn resolve_gid(group: &str) -> Result<Gid> {
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(Gid::from_raw(gid));
+    }
+
+    Group::from_name(group)
+        .map_err(|e| eyre!("Failed to look up group '{}': {}", group, e))?
+        .map(|group| group.gid)
+        .ok_or_else(|| eyre!("Unknown group '{}'", group))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_ids() {
+        assert_eq!(resolve_uid("0").unwrap(), Uid::from_raw(0));
+        assert_eq!(resolve_uid("root").unwrap(), Uid::from_raw(0));
+        assert_eq!(resolve_gid("0").unwrap(), Gid::from_raw(0));
+        assert!(resolve_uid("no-such-user-scinit").is_err());
+        assert!(resolve_gid("no-such-group-scinit").is_err());
+    }
+
+    #[test]
+    fn test_resolve_credentials() {
+        assert_eq!(Credentials::resolve(None, None).unwrap(), None);
+
+        let root = Credentials::resolve(Some("root"), None).unwrap().unwrap();
+        assert_eq!(root.uid, Uid::from_raw(0));
+        assert_eq!(root.gid, Gid::from_raw(0));
+        assert!(root.groups.contains(&Gid::from_raw(0)));
+        assert_eq!(root.name.as_deref(), Some("root"));
+        assert!(root.home.is_some());
+
+        let unknown = Credentials::resolve(Some("54321"), None).unwrap().unwrap();
+        assert_eq!(unknown.gid, Gid::from_raw(54321));
+        assert_eq!(unknown.groups, vec![Gid::from_raw(54321)]);
+        assert_eq!(unknown.name, None);
+
+        // An explicit group replaces the passwd primary group among the supplementary groups too
+        let nobody = ["nobody", "nogroup"]
+            .into_iter()
+            .find_map(|name| Group::from_name(name).ok().flatten())
+            .unwrap();
+        let switched = Credentials::resolve(Some("root"), Some(nobody.name.as_str())).unwrap().unwrap();
+        assert_eq!(switched.gid, nobody.gid);
+        assert!(switched.groups.contains(&nobody.gid));
+        let root_listed = Group::from_gid(Gid::from_raw(0))
+            .unwrap()
+            .is_some_and(|group| group.mem.iter().any(|member| member == "root"));
+        assert_eq!(switched.groups.contains(&Gid::from_raw(0)), root_listed);
+
+        let group_only = Credentials::resolve(None, Some("54322")).unwrap().unwrap();
+        assert_eq!(group_only.uid, Uid::current());
+        assert_eq!(group_only.gid, Gid::from_raw(54322));
+    }
+}